@@ -125,6 +125,7 @@ async fn main() {
         headers: headers::process_headers(args.headers),
         http3_prior_knowledge: args.http3_prior_knowledge,
         timeout,
+        ..Default::default()
     };
 
     let response = match args.method {
@@ -57,6 +57,7 @@ impl ImpitWrapper {
         .as_ref()
         .and_then(|init| init.force_http3)
         .unwrap_or_default(),
+      ..Default::default()
     });
 
     let body = request_init
@@ -83,8 +84,43 @@ impl ImpitWrapper {
           ErrorType::UrlMissingHostnameError => napi::Status::InvalidArg,
           ErrorType::UrlProtocolError => napi::Status::InvalidArg,
           ErrorType::UrlParsingError => napi::Status::InvalidArg,
+          ErrorType::InvalidHostHeader(_) => napi::Status::InvalidArg,
+          ErrorType::ProxyRequired => napi::Status::InvalidArg,
+          ErrorType::UnsupportedTlsExtension(_) => napi::Status::InvalidArg,
+          ErrorType::UnsupportedProxyAuthentication(_) => napi::Status::GenericFailure,
           ErrorType::Http3Disabled => napi::Status::GenericFailure,
           ErrorType::RequestError(_) => napi::Status::GenericFailure,
+          ErrorType::HostResolutionError(_) => napi::Status::GenericFailure,
+          ErrorType::Http3ConnectionError(_) => napi::Status::GenericFailure,
+          ErrorType::ExpectationFailed => napi::Status::GenericFailure,
+          ErrorType::CircuitOpen(_) => napi::Status::GenericFailure,
+          ErrorType::WebsocketPongTimeout => napi::Status::GenericFailure,
+          ErrorType::TooManyRedirects(_) => napi::Status::GenericFailure,
+          ErrorType::TlsError(_) => napi::Status::GenericFailure,
+          ErrorType::BodyCompressionError(_) => napi::Status::GenericFailure,
+          ErrorType::HostNotAllowed(_) => napi::Status::InvalidArg,
+          ErrorType::BlockedAddress(_) => napi::Status::InvalidArg,
+          ErrorType::IncompleteBody { .. } => napi::Status::GenericFailure,
+          ErrorType::HttpStatus { .. } => napi::Status::GenericFailure,
+          ErrorType::CertificateVerifierError(_) => napi::Status::InvalidArg,
+          ErrorType::Timeout => napi::Status::GenericFailure,
+          ErrorType::AllProxiesFailed => napi::Status::GenericFailure,
+          ErrorType::DeserializationError(_) => napi::Status::GenericFailure,
+          ErrorType::GrpcWebFramingError(_) => napi::Status::GenericFailure,
+          ErrorType::DecodingError(_) => napi::Status::GenericFailure,
+          ErrorType::RequestTooLarge { .. } => napi::Status::InvalidArg,
+          ErrorType::QueryNotPreserved { .. } => napi::Status::InvalidArg,
+          ErrorType::TunnelRejected(_) => napi::Status::GenericFailure,
+          ErrorType::ProxyConnectionError(_) => napi::Status::GenericFailure,
+          ErrorType::InvalidHarEntry(_) => napi::Status::InvalidArg,
+          ErrorType::FormNotFound(_) => napi::Status::InvalidArg,
+          ErrorType::ContentDecodingError(_) => napi::Status::GenericFailure,
+          ErrorType::RedirectBudgetExceeded(_) => napi::Status::GenericFailure,
+          ErrorType::UnixSocketError(_) => napi::Status::GenericFailure,
+          ErrorType::UnknownCipherSuite(_) => napi::Status::InvalidArg,
+          ErrorType::WebsocketError(_) => napi::Status::GenericFailure,
+          ErrorType::UriError(_) => napi::Status::InvalidArg,
+          ErrorType::HttpError(_) => napi::Status::GenericFailure,
         };
         let reason = format!("{:#?}", err);
         Err(napi::Error::new(status, reason))
@@ -264,6 +264,7 @@ impl AsyncClient {
             headers: headers.unwrap_or_default(),
             timeout: timeout.map(Duration::from_secs_f64),
             http3_prior_knowledge: force_http3.unwrap_or(false),
+            ..Default::default()
         };
 
         let (tx, rx) = oneshot::channel();
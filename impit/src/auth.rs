@@ -0,0 +1,339 @@
+use md5::Md5;
+use reqwest::{
+    header::{HeaderMap, WWW_AUTHENTICATE},
+    Method,
+};
+use rustls::crypto::aws_lc_rs;
+use sha2::{Digest, Sha256};
+
+/// The hash algorithm of a Digest challenge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    fn hash(&self, data: &str) -> String {
+        match self {
+            DigestAlgorithm::Md5 => format!("{:x}", Md5::digest(data.as_bytes())),
+            DigestAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data.as_bytes())),
+        }
+    }
+
+    fn hash_bytes(&self, data: &[u8]) -> String {
+        match self {
+            DigestAlgorithm::Md5 => format!("{:x}", Md5::digest(data)),
+            DigestAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data)),
+        }
+    }
+}
+
+/// A parsed `WWW-Authenticate: Digest ...` challenge (RFC 7616).
+#[derive(Debug, Clone)]
+pub(crate) struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    /// The algorithm name as sent by the server, echoed back in the response.
+    algorithm_name: Option<String>,
+    algorithm: DigestAlgorithm,
+    /// The `-sess` variants of the algorithms hash the nonces into the `HA1` value.
+    session: bool,
+    qop: Vec<String>,
+}
+
+impl DigestChallenge {
+    /// Returns the first supported Digest challenge from the `WWW-Authenticate` headers.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(Self::parse)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let (scheme, params) = value.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let params = parse_params(params);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(param_name, _)| param_name.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.to_owned())
+        };
+
+        let algorithm_name = param("algorithm");
+        let (algorithm, session) = match algorithm_name.as_deref().map(str::to_ascii_uppercase) {
+            None => (DigestAlgorithm::Md5, false),
+            Some(name) => match name.as_str() {
+                "MD5" => (DigestAlgorithm::Md5, false),
+                "MD5-SESS" => (DigestAlgorithm::Md5, true),
+                "SHA-256" => (DigestAlgorithm::Sha256, false),
+                "SHA-256-SESS" => (DigestAlgorithm::Sha256, true),
+                _ => return None,
+            },
+        };
+
+        Some(DigestChallenge {
+            realm: param("realm").unwrap_or_default(),
+            nonce: param("nonce")?,
+            opaque: param("opaque"),
+            algorithm_name,
+            algorithm,
+            session,
+            qop: param("qop")
+                .map(|qop| {
+                    qop.split(',')
+                        .map(|qop| qop.trim().to_ascii_lowercase())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Computes the `Authorization` header value answering the challenge.
+    ///
+    /// `uri` is the request target (path and query), `body` is needed for the `auth-int` quality of protection.
+    pub fn authorization(
+        &self,
+        username: &str,
+        password: &str,
+        method: &Method,
+        uri: &str,
+        body: Option<&[u8]>,
+    ) -> String {
+        // `auth` is preferred, `auth-int` requires the (non-streamed) body.
+        let qop = if self.qop.iter().any(|qop| qop == "auth") {
+            Some("auth")
+        } else if body.is_some() && self.qop.iter().any(|qop| qop == "auth-int") {
+            Some("auth-int")
+        } else {
+            None
+        };
+
+        // Every challenge is answered only once, so the nonce count always starts at 1.
+        let nc = "00000001";
+        let cnonce = new_cnonce();
+        let hash = |data: &str| self.algorithm.hash(data);
+
+        let mut ha1 = hash(&format!("{}:{}:{}", username, self.realm, password));
+        if self.session {
+            ha1 = hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+
+        let ha2 = match qop {
+            Some("auth-int") => hash(&format!(
+                "{}:{}:{}",
+                method,
+                uri,
+                self.algorithm.hash_bytes(body.unwrap_or_default())
+            )),
+            _ => hash(&format!("{}:{}", method, uri)),
+        };
+
+        let response = match qop {
+            Some(qop) => hash(&format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1, self.nonce, nc, cnonce, qop, ha2
+            )),
+            None => hash(&format!("{}:{}:{}", ha1, self.nonce, ha2)),
+        };
+
+        let mut authorization = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+            quote(username),
+            quote(&self.realm),
+            quote(&self.nonce),
+            quote(uri)
+        );
+
+        if let Some(algorithm_name) = &self.algorithm_name {
+            authorization.push_str(&format!(", algorithm={}", algorithm_name));
+        }
+
+        authorization.push_str(&format!(", response=\"{}\"", response));
+
+        if let Some(qop) = qop {
+            authorization.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+        }
+
+        if let Some(opaque) = &self.opaque {
+            authorization.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+        }
+
+        authorization
+    }
+}
+
+/// Parses the comma-separated `name=value` (or `name="quoted value"`) parameters of a challenge.
+fn parse_params(params: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    let mut chars = params.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+
+        let name: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        if name.is_empty() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                value.push(c);
+            }
+        }
+
+        result.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    result
+}
+
+/// Escapes the quotes and backslashes for a quoted-string parameter.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn new_cnonce() -> String {
+    let mut bytes = [0u8; 16];
+    aws_lc_rs::default_provider()
+        .secure_random
+        .fill(&mut bytes)
+        .expect("the system random number generator is unavailable");
+
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    const RFC_CHALLENGE: &str = r#"Digest realm="testrealm@host.com", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#;
+
+    fn challenge(value: &str) -> DigestChallenge {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            WWW_AUTHENTICATE,
+            HeaderValue::from_static("Basic realm=\"x\""),
+        );
+        headers.append(WWW_AUTHENTICATE, HeaderValue::from_str(value).unwrap());
+
+        DigestChallenge::from_headers(&headers).unwrap()
+    }
+
+    /// Returns the value of the `name` parameter of the `Authorization` header value.
+    fn authorization_param(authorization: &str, name: &str) -> String {
+        parse_params(authorization.strip_prefix("Digest ").unwrap())
+            .into_iter()
+            .find(|(param_name, _)| param_name == name)
+            .map(|(_, value)| value)
+            .unwrap()
+    }
+
+    #[test]
+    fn challenges_without_qop_are_answered() {
+        let authorization = challenge(RFC_CHALLENGE).authorization(
+            "Mufasa",
+            "Circle Of Life",
+            &Method::GET,
+            "/dir/index.html",
+            None,
+        );
+
+        assert_eq!(
+            authorization,
+            "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+             uri=\"/dir/index.html\", response=\"670fd8c2df070c60b045671b8b24ff02\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""
+        );
+    }
+
+    #[test]
+    fn auth_qop_is_preferred() {
+        let challenge = challenge(&format!("{}, qop=\"auth-int, auth\"", RFC_CHALLENGE));
+        let authorization = challenge.authorization(
+            "Mufasa",
+            "Circle Of Life",
+            &Method::GET,
+            "/dir/index.html",
+            Some(b"body".as_slice()),
+        );
+
+        assert_eq!(authorization_param(&authorization, "qop"), "auth");
+        assert_eq!(authorization_param(&authorization, "nc"), "00000001");
+
+        let cnonce = authorization_param(&authorization, "cnonce");
+        let md5 = |data: &str| DigestAlgorithm::Md5.hash(data);
+        let expected = md5(&format!(
+            "{}:dcd98b7102dd2f0e8b11d0f600bfb0c093:00000001:{}:auth:{}",
+            md5("Mufasa:testrealm@host.com:Circle Of Life"),
+            cnonce,
+            md5("GET:/dir/index.html")
+        ));
+
+        assert_eq!(cnonce.len(), 32);
+        assert_eq!(authorization_param(&authorization, "response"), expected);
+    }
+
+    #[test]
+    fn auth_int_qop_requires_the_body() {
+        let challenge =
+            challenge(r#"Digest realm="r", nonce="n", qop="auth-int", algorithm=SHA-256-sess"#);
+
+        let without_body = challenge.authorization("user", "pass", &Method::POST, "/", None);
+        assert!(!without_body.contains("qop="));
+
+        let with_body =
+            challenge.authorization("user", "pass", &Method::POST, "/", Some(b"{}".as_slice()));
+        assert_eq!(authorization_param(&with_body, "qop"), "auth-int");
+        assert_eq!(authorization_param(&with_body, "algorithm"), "SHA-256-sess");
+
+        let cnonce = authorization_param(&with_body, "cnonce");
+        let sha256 = |data: &str| DigestAlgorithm::Sha256.hash(data);
+        let ha1 = sha256(&format!("{}:n:{}", sha256("user:r:pass"), cnonce));
+        let ha2 = sha256(&format!("POST:/:{}", sha256("{}")));
+
+        assert_eq!(
+            authorization_param(&with_body, "response"),
+            sha256(&format!("{}:n:00000001:{}:auth-int:{}", ha1, cnonce, ha2))
+        );
+    }
+
+    #[test]
+    fn unsupported_challenges_are_skipped() {
+        assert!(DigestChallenge::parse("Basic realm=\"x\"").is_none());
+        assert!(
+            DigestChallenge::parse("Digest realm=\"x\", nonce=\"n\", algorithm=SHA-512").is_none()
+        );
+        assert!(DigestChallenge::parse("Digest realm=\"x\"").is_none());
+        assert!(DigestChallenge::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn quoted_values_are_escaped() {
+        let challenge = challenge(r#"Digest realm="a \"quoted\" realm", nonce=n"#);
+        let authorization = challenge.authorization("us\"er", "pass", &Method::GET, "/", None);
+
+        assert!(authorization.starts_with(
+            r#"Digest username="us\"er", realm="a \"quoted\" realm", nonce="n", uri="/""#
+        ));
+    }
+}
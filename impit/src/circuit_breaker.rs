@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use tokio::time::Instant;
+
+use crate::impit::CircuitBreakerConfig;
+
+/// The state of the circuit for a single host.
+enum CircuitState {
+    /// Requests are allowed. Holds the number of consecutive failures.
+    Closed(usize),
+    /// Requests are short-circuited until the given instant.
+    Open(Instant),
+    /// The cooldown has passed, the next request probes whether the host has recovered.
+    HalfOpen,
+}
+
+/// Tracks the consecutive request failures per host and short-circuits requests to hosts that keep failing.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    hosts: HashMap<String, CircuitState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            config,
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Returns `false` if the circuit for the host is open, i.e. the request should be short-circuited.
+    ///
+    /// After the cooldown, the circuit is half-opened and the request is allowed as a probe.
+    pub fn allow_request(&mut self, host: &str) -> bool {
+        match self.hosts.get(host) {
+            Some(CircuitState::Open(until)) if Instant::now() < *until => false,
+            Some(CircuitState::Open(_)) => {
+                self.hosts.insert(host.to_owned(), CircuitState::HalfOpen);
+                true
+            }
+            _ => true,
+        }
+    }
+
+    pub fn record_success(&mut self, host: &str) {
+        self.hosts.insert(host.to_owned(), CircuitState::Closed(0));
+    }
+
+    pub fn record_failure(&mut self, host: &str) {
+        let failures = match self.hosts.get(host) {
+            Some(CircuitState::Closed(failures)) => failures + 1,
+            // A failed probe opens the circuit right away.
+            _ => self.config.failure_threshold,
+        };
+
+        let state = if failures >= self.config.failure_threshold {
+            CircuitState::Open(Instant::now() + self.config.cooldown)
+        } else {
+            CircuitState::Closed(failures)
+        };
+
+        self.hosts.insert(host.to_owned(), state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const COOLDOWN: Duration = Duration::from_secs(30);
+
+    fn circuit_breaker() -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            cooldown: COOLDOWN,
+        })
+    }
+
+    fn fail(circuit_breaker: &mut CircuitBreaker, host: &str, times: usize) {
+        for _ in 0..times {
+            circuit_breaker.record_failure(host);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn circuit_opens_after_consecutive_failures() {
+        let mut circuit_breaker = circuit_breaker();
+
+        fail(&mut circuit_breaker, "example.com", 2);
+        assert!(circuit_breaker.allow_request("example.com"));
+
+        fail(&mut circuit_breaker, "example.com", 1);
+        assert!(!circuit_breaker.allow_request("example.com"));
+        assert!(circuit_breaker.allow_request("other.example"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn successes_reset_the_failure_count() {
+        let mut circuit_breaker = circuit_breaker();
+
+        fail(&mut circuit_breaker, "example.com", 2);
+        circuit_breaker.record_success("example.com");
+        fail(&mut circuit_breaker, "example.com", 2);
+
+        assert!(circuit_breaker.allow_request("example.com"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn successful_probes_close_the_circuit() {
+        let mut circuit_breaker = circuit_breaker();
+        fail(&mut circuit_breaker, "example.com", 3);
+
+        tokio::time::advance(COOLDOWN - Duration::from_secs(1)).await;
+        assert!(!circuit_breaker.allow_request("example.com"));
+
+        // Half-open after the cooldown, the probe is let through.
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(circuit_breaker.allow_request("example.com"));
+
+        circuit_breaker.record_success("example.com");
+        fail(&mut circuit_breaker, "example.com", 2);
+        assert!(circuit_breaker.allow_request("example.com"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_probes_reopen_the_circuit() {
+        let mut circuit_breaker = circuit_breaker();
+        fail(&mut circuit_breaker, "example.com", 3);
+
+        tokio::time::advance(COOLDOWN).await;
+        assert!(circuit_breaker.allow_request("example.com"));
+
+        // A single failure is enough, and the cooldown starts over.
+        fail(&mut circuit_breaker, "example.com", 1);
+        assert!(!circuit_breaker.allow_request("example.com"));
+
+        tokio::time::advance(COOLDOWN - Duration::from_secs(1)).await;
+        assert!(!circuit_breaker.allow_request("example.com"));
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(circuit_breaker.allow_request("example.com"));
+    }
+}
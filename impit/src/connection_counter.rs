@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Counts the connections established by the `reqwest` connector, i.e. the ones that weren't taken from the connection pool.
+///
+/// Used as a connector layer, so every established connection is counted once - plain HTTP, TLS (resumed sessions included)
+/// and proxy tunnels alike. The HTTP/3 connections don't go through the connector.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionCounter {
+    count: Arc<AtomicU64>,
+}
+
+impl ConnectionCounter {
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl<S> Layer<S> for ConnectionCounter {
+    type Service = CountingConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountingConnector {
+            inner,
+            count: self.count.clone(),
+        }
+    }
+}
+
+/// The connector service wrapped by the [`ConnectionCounter`].
+#[derive(Debug, Clone)]
+pub struct CountingConnector<S> {
+    inner: S,
+    count: Arc<AtomicU64>,
+}
+
+impl<S, R> Service<R> for CountingConnector<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let count = self.count.clone();
+        let connecting = self.inner.call(request);
+
+        Box::pin(async move {
+            let connection = connecting.await?;
+            // Only the established connections are counted, the failed attempts never carry a request.
+            count.fetch_add(1, Ordering::Relaxed);
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Connects successfully to the even ports only.
+    #[derive(Clone)]
+    struct EvenPortConnector;
+
+    impl Service<u16> for EvenPortConnector {
+        type Response = u16;
+        type Error = &'static str;
+        type Future = std::future::Ready<Result<u16, &'static str>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, port: u16) -> Self::Future {
+            std::future::ready(if port % 2 == 0 {
+                Ok(port)
+            } else {
+                Err("connection refused")
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn only_established_connections_are_counted() {
+        let counter = ConnectionCounter::default();
+        let mut connector = counter.layer(EvenPortConnector);
+
+        assert_eq!(connector.call(80).await, Ok(80));
+        assert_eq!(connector.call(81).await, Err("connection refused"));
+        assert_eq!(connector.call(8080).await, Ok(8080));
+
+        assert_eq!(counter.count(), 2);
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_count() {
+        let counter = ConnectionCounter::default();
+        let mut first = counter.layer(EvenPortConnector);
+        let mut second = counter.clone().layer(EvenPortConnector);
+
+        first.call(80).await.unwrap();
+        second.call(443).await.unwrap_err();
+        second.call(444).await.unwrap();
+
+        assert_eq!(counter.count(), 2);
+    }
+}
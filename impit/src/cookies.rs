@@ -0,0 +1,313 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    header::HeaderValue,
+};
+use url::Url;
+
+/// The future returned by the [`CookieStorage`] methods.
+pub type StorageFuture<'a, T> = Pin<Box<dyn Future<Output = io::Result<T>> + Send + 'a>>;
+
+/// A cookie as received from the server, i.e. the `Set-Cookie` header value together with the URL of the response.
+///
+/// Replaying the header for the URL restores the cookie with all its attributes (domain, path, expiration, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredCookie {
+    /// The URL of the response that set the cookie.
+    pub url: Url,
+    /// The `Set-Cookie` header value.
+    pub set_cookie: String,
+}
+
+impl StoredCookie {
+    /// The cookies with the same name, domain and path replace each other (RFC 6265, section 5.3, step 11).
+    fn replaces(&self, other: &StoredCookie) -> bool {
+        self.identity() == other.identity()
+    }
+
+    /// Returns the name, the domain and the path of the cookie.
+    ///
+    /// Host-only cookies (without the `Domain` attribute) belong to the URL host, cookies without a valid `Path` attribute
+    /// to the default path of the URL.
+    fn identity(&self) -> (Option<String>, String, String) {
+        let mut parts = self.set_cookie.split(';');
+
+        let name = parts
+            .next()
+            .and_then(|pair| pair.split_once('='))
+            .map(|(name, _)| name.trim().to_owned());
+
+        let mut domain = None;
+        let mut path = None;
+
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !value.is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_ascii_lowercase());
+                }
+                "path" if value.starts_with('/') => path = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+
+        let domain =
+            domain.unwrap_or_else(|| self.url.host_str().unwrap_or_default().to_ascii_lowercase());
+        let path = path.unwrap_or_else(|| default_path(&self.url).to_owned());
+
+        (name, domain, path)
+    }
+}
+
+/// Returns the default cookie path for the URL, i.e. the path up to (but not including) its last `/` (RFC 6265, section 5.1.4).
+fn default_path(url: &Url) -> &str {
+    match url.path().rfind('/') {
+        Some(0) | None => "/",
+        Some(end) => &url.path()[..end],
+    }
+}
+
+/// A backing store for persisting the cookies between runs, see [`ImpitBuilder::with_cookie_storage`](crate::impit::ImpitBuilder::with_cookie_storage).
+pub trait CookieStorage: Send + Sync {
+    /// Loads the previously saved cookies. A missing store should result in an empty list, not an error.
+    fn load(&self) -> StorageFuture<'_, Vec<StoredCookie>>;
+
+    /// Replaces the saved cookies with `cookies`.
+    fn save(&self, cookies: Vec<StoredCookie>) -> StorageFuture<'_, ()>;
+}
+
+/// A [`CookieStorage`] keeping the cookies in a JSON file.
+pub struct FileCookieStorage {
+    path: PathBuf,
+}
+
+impl FileCookieStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileCookieStorage { path: path.into() }
+    }
+}
+
+impl CookieStorage for FileCookieStorage {
+    fn load(&self) -> StorageFuture<'_, Vec<StoredCookie>> {
+        Box::pin(async move {
+            let content = match tokio::fs::read(&self.path).await {
+                Ok(content) => content,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+                Err(e) => return Err(e),
+            };
+
+            let entries: Vec<serde_json::Value> = serde_json::from_slice(&content)?;
+
+            Ok(entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(StoredCookie {
+                        url: Url::parse(entry["url"].as_str()?).ok()?,
+                        set_cookie: entry["set_cookie"].as_str()?.to_owned(),
+                    })
+                })
+                .collect())
+        })
+    }
+
+    fn save(&self, cookies: Vec<StoredCookie>) -> StorageFuture<'_, ()> {
+        Box::pin(async move {
+            let entries: Vec<serde_json::Value> = cookies
+                .iter()
+                .map(|cookie| {
+                    serde_json::json!({
+                        "url": cookie.url.as_str(),
+                        "set_cookie": cookie.set_cookie,
+                    })
+                })
+                .collect();
+
+            // Writing to a temporary file first, so a crash mid-write doesn't corrupt the store.
+            let temp_path = self.path.with_extension("tmp");
+            tokio::fs::write(&temp_path, serde_json::to_vec_pretty(&entries)?).await?;
+            tokio::fs::rename(&temp_path, &self.path).await
+        })
+    }
+}
+
+/// A cloneable handle to the [`CookieStorage`], stored in the [`ImpitBuilder`](crate::impit::ImpitBuilder).
+#[derive(Clone)]
+pub(crate) struct SharedCookieStorage(pub(crate) Arc<dyn CookieStorage>);
+
+impl fmt::Debug for SharedCookieStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedCookieStorage")
+    }
+}
+
+/// The cookie store of an [`Impit`](crate::impit::Impit) instance.
+///
+/// Wraps the `reqwest` [`Jar`] - which can't list its cookies - and records the received `Set-Cookie` headers for the [`CookieStorage`].
+/// This includes the cookies set by the redirect responses, which `reqwest` handles internally.
+#[derive(Default)]
+pub(crate) struct CookieJar {
+    jar: Jar,
+    /// Whether the received cookies are recorded, i.e. a [`CookieStorage`] is configured.
+    recording: bool,
+    records: Mutex<Vec<StoredCookie>>,
+    /// Whether there are new records since the last save.
+    dirty: AtomicBool,
+}
+
+impl CookieJar {
+    pub fn new(recording: bool) -> Self {
+        CookieJar {
+            recording,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the cookies loaded from the storage (without marking the jar as dirty).
+    pub fn restore(&self, cookies: Vec<StoredCookie>) {
+        for cookie in &cookies {
+            self.jar.add_cookie_str(&cookie.set_cookie, &cookie.url);
+        }
+
+        self.records.lock().unwrap().extend(cookies);
+    }
+
+    /// Returns the recorded cookies if there are new ones since the last call.
+    pub fn take_changes(&self) -> Option<Vec<StoredCookie>> {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some(self.records.lock().unwrap().clone())
+    }
+
+    fn record(&self, cookie: StoredCookie) {
+        let mut records = self.records.lock().unwrap();
+        records.retain(|record| !cookie.replaces(record));
+        records.push(cookie);
+
+        self.dirty.store(true, Ordering::Release);
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookie_headers: Vec<&HeaderValue> = cookie_headers.collect();
+
+        if self.recording {
+            for header in &cookie_headers {
+                if let Ok(set_cookie) = header.to_str() {
+                    self.record(StoredCookie {
+                        url: url.clone(),
+                        set_cookie: set_cookie.to_owned(),
+                    });
+                }
+            }
+        }
+
+        self.jar.set_cookies(&mut cookie_headers.into_iter(), url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.jar.cookies(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(url: &str, set_cookie: &str) -> StoredCookie {
+        StoredCookie {
+            url: Url::parse(url).unwrap(),
+            set_cookie: set_cookie.to_owned(),
+        }
+    }
+
+    #[test]
+    fn same_name_domain_and_path_replace() {
+        let old = cookie("https://example.com/a/b", "id=1; Path=/a");
+        let new = cookie("https://example.com/a/c", "id=2; Path=/a; Max-Age=60");
+
+        assert!(new.replaces(&old));
+    }
+
+    #[test]
+    fn different_paths_coexist() {
+        let root = cookie("https://example.com/", "id=1; Path=/");
+        let nested = cookie("https://example.com/", "id=2; Path=/app");
+
+        assert!(!nested.replaces(&root));
+    }
+
+    #[test]
+    fn default_path_comes_from_the_url() {
+        let explicit = cookie("https://example.com/", "id=1; Path=/docs");
+        let implicit = cookie("https://example.com/docs/page", "id=2");
+        let root = cookie("https://example.com/page", "id=3");
+
+        assert!(implicit.replaces(&explicit));
+        assert!(!root.replaces(&explicit));
+    }
+
+    #[test]
+    fn domain_cookies_differ_from_host_only_cookies_of_subdomains() {
+        let domain = cookie("https://www.example.com/", "id=1; Domain=.Example.com");
+        let same_domain = cookie("https://api.example.com/", "id=2; domain=example.com");
+        let host_only = cookie("https://www.example.com/", "id=3");
+
+        assert!(same_domain.replaces(&domain));
+        assert!(!host_only.replaces(&domain));
+    }
+
+    #[test]
+    fn names_are_case_sensitive() {
+        let lower = cookie("https://example.com/", "id=1");
+        let upper = cookie("https://example.com/", "ID=2");
+
+        assert!(!upper.replaces(&lower));
+    }
+
+    #[test]
+    fn jar_records_replacements_and_restored_cookies() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let jar = CookieJar::new(true);
+
+        jar.restore(vec![cookie("https://example.com/", "id=1")]);
+        assert_eq!(jar.take_changes(), None);
+
+        jar.set_cookies(
+            &mut [
+                HeaderValue::from_static("id=2"),
+                HeaderValue::from_static("theme=dark; Path=/"),
+            ]
+            .iter(),
+            &url,
+        );
+
+        let changes = jar.take_changes().unwrap();
+        assert_eq!(
+            changes
+                .iter()
+                .map(|cookie| cookie.set_cookie.as_str())
+                .collect::<Vec<_>>(),
+            ["id=2", "theme=dark; Path=/"]
+        );
+        assert_eq!(jar.take_changes(), None);
+
+        let sent = jar.cookies(&url).unwrap();
+        assert!(sent.to_str().unwrap().contains("id=2"));
+    }
+}
@@ -0,0 +1,279 @@
+use scraper::{ElementRef, Html, Selector};
+
+use crate::{impit::ErrorType, random::Rng};
+
+/// An HTML `<form>` with the values of its fields, as the browser would submit it.
+pub(crate) struct HtmlForm {
+    /// The `action` attribute (possibly relative). The form is submitted to the page URL if it's missing.
+    pub action: Option<String>,
+    /// The uppercase `method` attribute, `GET` or `POST`.
+    pub method: String,
+    /// The `enctype` attribute, used for the `POST` forms.
+    pub enctype: String,
+    /// The `(name, value)` pairs of the successful controls, in the document order.
+    pub fields: Vec<(String, String)>,
+}
+
+impl HtmlForm {
+    /// Finds the first form matching the CSS `selector` in the `html` document.
+    ///
+    /// Fails with [`ErrorType::FormNotFound`] if the selector is invalid or doesn't match a `<form>` element.
+    pub fn find(html: &str, selector: &str) -> Result<Self, ErrorType> {
+        let not_found = || ErrorType::FormNotFound(selector.to_string());
+
+        let dom = Html::parse_document(html);
+        let form_selector = Selector::parse(selector).map_err(|_| not_found())?;
+        let form = dom
+            .select(&form_selector)
+            .find(|element| element.value().name() == "form")
+            .ok_or_else(not_found)?;
+
+        let attribute = |name: &str| form.value().attr(name).map(str::trim);
+
+        let method = match attribute("method") {
+            Some(method) if method.eq_ignore_ascii_case("post") => "POST",
+            _ => "GET",
+        };
+
+        Ok(HtmlForm {
+            action: attribute("action")
+                .filter(|action| !action.is_empty())
+                .map(str::to_string),
+            method: method.to_string(),
+            enctype: attribute("enctype")
+                .unwrap_or("application/x-www-form-urlencoded")
+                .to_ascii_lowercase(),
+            fields: form_fields(form),
+        })
+    }
+
+    /// Replaces the values of the fields named like the `overrides` (or adds the fields that are missing).
+    pub fn merge(&mut self, overrides: &[(&str, &str)]) {
+        for (name, value) in overrides {
+            match self.fields.iter().position(|(field, _)| field == name) {
+                Some(position) => {
+                    self.fields[position].1 = value.to_string();
+
+                    // Only the first field with the name is kept, e.g. for a checkbox group.
+                    let mut index = 0;
+                    self.fields.retain(|(field, _)| {
+                        let keep = index <= position || field != name;
+                        index += 1;
+                        keep
+                    });
+                }
+                None => self.fields.push((name.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    /// Encodes the fields as the request body, returning the `Content-Type` header value and the body.
+    pub fn encode(&self) -> (String, Vec<u8>) {
+        if self.enctype != "multipart/form-data" {
+            return (
+                "application/x-www-form-urlencoded".to_string(),
+                self.urlencoded().into_bytes(),
+            );
+        }
+
+        let boundary = format!("----WebKitFormBoundary{:016x}", Rng::default().next_u64());
+        let mut body = vec![];
+
+        for (name, value) in &self.fields {
+            body.extend_from_slice(
+                format!(
+                    "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{value}\r\n",
+                    name.replace('"', "%22")
+                )
+                .as_bytes(),
+            );
+        }
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+        (format!("multipart/form-data; boundary={boundary}"), body)
+    }
+
+    /// Returns the fields in the `application/x-www-form-urlencoded` format.
+    pub fn urlencoded(&self) -> String {
+        url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.fields)
+            .finish()
+    }
+}
+
+/// Collects the values of the successful controls of the form, i.e. the ones the browser would submit.
+///
+/// The submit buttons and file inputs are skipped, as are the disabled and unnamed controls.
+fn form_fields(form: ElementRef) -> Vec<(String, String)> {
+    let controls = Selector::parse("input, select, textarea").unwrap();
+    let options = Selector::parse("option").unwrap();
+
+    let mut fields = vec![];
+
+    for control in form.select(&controls) {
+        let element = control.value();
+
+        let Some(name) = element.attr("name").filter(|name| !name.is_empty()) else {
+            continue;
+        };
+
+        if element.attr("disabled").is_some() {
+            continue;
+        }
+
+        match element.name() {
+            "input" => {
+                let input_type = element.attr("type").unwrap_or("text").to_ascii_lowercase();
+
+                match input_type.as_str() {
+                    "submit" | "button" | "image" | "reset" | "file" => {}
+                    "checkbox" | "radio" => {
+                        if element.attr("checked").is_some() {
+                            let value = element.attr("value").unwrap_or("on");
+                            fields.push((name.to_string(), value.to_string()));
+                        }
+                    }
+                    _ => {
+                        let value = element.attr("value").unwrap_or_default();
+                        fields.push((name.to_string(), value.to_string()));
+                    }
+                }
+            }
+            "textarea" => fields.push((name.to_string(), control.text().collect())),
+            "select" => {
+                let option_value = |option: &ElementRef| {
+                    option
+                        .value()
+                        .attr("value")
+                        .map(str::to_string)
+                        .unwrap_or_else(|| option.text().collect::<String>().trim().to_string())
+                };
+
+                let all_options: Vec<ElementRef> = control.select(&options).collect();
+                let selected: Vec<&ElementRef> = all_options
+                    .iter()
+                    .filter(|option| option.value().attr("selected").is_some())
+                    .collect();
+
+                if element.attr("multiple").is_some() {
+                    for option in selected {
+                        fields.push((name.to_string(), option_value(option)));
+                    }
+                } else if let Some(option) = selected.last().copied().or(all_options.first()) {
+                    // Without a selected option, the first one is submitted.
+                    fields.push((name.to_string(), option_value(option)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGIN_FORM: &str = r#"
+        <form id="login" action="/session" method="post">
+            <input type="hidden" name="csrf" value="token">
+            <input name="user" value="">
+            <input type="checkbox" name="remember" checked>
+            <input type="checkbox" name="newsletter" value="yes">
+            <input name="disabled" value="x" disabled>
+            <input type="submit" name="submit" value="Log in">
+            <select name="lang"><option value="en">English</option><option selected>Deutsch</option></select>
+            <select name="empty"><option>First</option><option>Second</option></select>
+            <select name="tags" multiple><option selected>a</option><option>b</option><option selected>c</option></select>
+            <textarea name="note">Hello</textarea>
+        </form>
+    "#;
+
+    #[test]
+    fn successful_controls_are_collected() {
+        let form = HtmlForm::find(LOGIN_FORM, "#login").unwrap();
+
+        assert_eq!(form.action.as_deref(), Some("/session"));
+        assert_eq!(form.method, "POST");
+        assert_eq!(form.enctype, "application/x-www-form-urlencoded");
+        assert_eq!(
+            form.fields,
+            [
+                ("csrf", "token"),
+                ("user", ""),
+                ("remember", "on"),
+                ("lang", "Deutsch"),
+                ("empty", "First"),
+                ("tags", "a"),
+                ("tags", "c"),
+                ("note", "Hello"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_forms_are_reported() {
+        assert!(matches!(
+            HtmlForm::find(LOGIN_FORM, "#signup"),
+            Err(ErrorType::FormNotFound(_))
+        ));
+        assert!(matches!(
+            HtmlForm::find(LOGIN_FORM, "input"),
+            Err(ErrorType::FormNotFound(_))
+        ));
+        assert!(matches!(
+            HtmlForm::find(LOGIN_FORM, "[["),
+            Err(ErrorType::FormNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn overrides_replace_and_add_fields() {
+        let mut form = HtmlForm::find(LOGIN_FORM, "form").unwrap();
+
+        form.merge(&[("user", "jane doe"), ("tags", "b"), ("otp", "123")]);
+
+        assert_eq!(
+            form.urlencoded(),
+            "csrf=token&user=jane+doe&remember=on&lang=Deutsch&empty=First&tags=b&note=Hello&otp=123"
+        );
+    }
+
+    #[test]
+    fn forms_are_encoded_by_enctype() {
+        let mut form = HtmlForm::find(
+            r#"<form enctype="Multipart/Form-Data"><input name="a" value="1"><input name='b"c' value="2"></form>"#,
+            "form",
+        )
+        .unwrap();
+
+        assert_eq!(form.method, "GET");
+        assert_eq!(form.action, None);
+
+        let (content_type, body) = form.encode();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n\
+                 --{boundary}\r\nContent-Disposition: form-data; name=\"b%22c\"\r\n\r\n2\r\n\
+                 --{boundary}--\r\n"
+            )
+        );
+
+        form.enctype = "text/plain".to_string();
+
+        assert_eq!(
+            form.encode(),
+            (
+                "application/x-www-form-urlencoded".to_string(),
+                b"a=1&b%22c=2".to_vec()
+            )
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::impit::ErrorType;
+
+/// The content type of the binary (protobuf) gRPC-web messages.
+pub const GRPC_WEB_CONTENT_TYPE: &str = "application/grpc-web+proto";
+
+/// The flag of the frames carrying a message.
+const DATA_FRAME: u8 = 0x00;
+/// The flag bit marking the frame carrying the trailers.
+const TRAILER_FRAME: u8 = 0x80;
+/// The flag bit marking a compressed frame.
+const COMPRESSED_FRAME: u8 = 0x01;
+
+/// The result of a unary gRPC-web call, as returned by [`Impit::grpc_web_call`](crate::impit::Impit::grpc_web_call).
+#[derive(Debug, Clone, Default)]
+pub struct GrpcWebResponse {
+    /// The (serialized) response messages, in the order they were received.
+    pub messages: Vec<Bytes>,
+    /// The `grpc-status` code. `0` means success, see https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+    ///
+    /// `None` if the server didn't send the status at all (which usually means the response didn't come from a gRPC server).
+    pub status: Option<u32>,
+    /// The (percent-encoded) `grpc-message` describing the error, if any.
+    pub message: Option<String>,
+    /// All the trailers, from the trailer frame, the HTTP trailers and the headers of "trailers-only" responses.
+    pub trailers: HeaderMap,
+}
+
+/// Frames the serialized message for the request body - a flag byte (uncompressed data) followed by the big-endian message length.
+pub fn encode_message(message: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(message.len() + 5);
+    frame.push(DATA_FRAME);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// Splits the gRPC-web response body into the messages and the trailers sent in the trailer frame.
+///
+/// Returns [`ErrorType::GrpcWebFramingError`] for truncated and compressed frames.
+pub fn decode_frames(mut body: &[u8]) -> Result<(Vec<Bytes>, HeaderMap), ErrorType> {
+    let mut messages = vec![];
+    let mut trailers = HeaderMap::new();
+
+    while !body.is_empty() {
+        if body.len() < 5 {
+            return Err(ErrorType::GrpcWebFramingError(
+                "truncated frame header".to_string(),
+            ));
+        }
+
+        let flag = body[0];
+        let length = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+        let Some(payload) = body.get(5..5 + length) else {
+            return Err(ErrorType::GrpcWebFramingError(format!(
+                "the frame declares {length} bytes, but only {} were received",
+                body.len() - 5
+            )));
+        };
+
+        if flag & COMPRESSED_FRAME != 0 {
+            return Err(ErrorType::GrpcWebFramingError(
+                "compressed frames are not supported".to_string(),
+            ));
+        }
+
+        if flag & TRAILER_FRAME != 0 {
+            trailers.extend(parse_trailers(payload));
+        } else {
+            messages.push(Bytes::copy_from_slice(payload));
+        }
+
+        body = &body[5 + length..];
+    }
+
+    Ok((messages, trailers))
+}
+
+/// Parses the trailer frame payload, encoded as HTTP/1.1 header lines (`grpc-status: 0\r\n`). Invalid lines are skipped.
+fn parse_trailers(payload: &[u8]) -> HeaderMap {
+    String::from_utf8_lossy(payload)
+        .split("\r\n")
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            let name = HeaderName::from_bytes(name.trim().to_ascii_lowercase().as_bytes()).ok()?;
+            let value = HeaderValue::from_str(value.trim()).ok()?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+impl GrpcWebResponse {
+    /// Builds the response from the decoded frames and the HTTP trailers (and headers).
+    pub(crate) fn new(messages: Vec<Bytes>, trailers: HeaderMap) -> Self {
+        let trailer = |name: &str| {
+            trailers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        GrpcWebResponse {
+            status: trailer("grpc-status").and_then(|status| status.parse().ok()),
+            message: trailer("grpc-message"),
+            messages,
+            trailers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trailer_frame(trailers: &str) -> Vec<u8> {
+        let mut frame = vec![TRAILER_FRAME];
+        frame.extend_from_slice(&(trailers.len() as u32).to_be_bytes());
+        frame.extend_from_slice(trailers.as_bytes());
+        frame
+    }
+
+    fn framing_error(body: &[u8]) -> String {
+        match decode_frames(body) {
+            Err(ErrorType::GrpcWebFramingError(error)) => error,
+            result => panic!("expected a framing error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn messages_are_length_prefixed() {
+        assert_eq!(encode_message(b"hi"), [0, 0, 0, 0, 2, b'h', b'i']);
+        assert_eq!(encode_message(b""), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn encoded_messages_decode_back() {
+        let body = [
+            encode_message(b"first"),
+            encode_message(b""),
+            encode_message(b"third"),
+        ]
+        .concat();
+
+        let (messages, trailers) = decode_frames(&body).unwrap();
+        assert_eq!(messages, ["first", "", "third"].map(Bytes::from));
+        assert!(trailers.is_empty());
+    }
+
+    #[test]
+    fn trailer_frames_are_parsed_into_the_trailers() {
+        let body = [
+            encode_message(b"reply"),
+            trailer_frame("grpc-status: 5\r\nGrpc-Message: not%20found\r\ninvalid line\r\n"),
+        ]
+        .concat();
+
+        let (messages, trailers) = decode_frames(&body).unwrap();
+        assert_eq!(messages, [Bytes::from("reply")]);
+        assert_eq!(trailers.len(), 2);
+
+        let response = GrpcWebResponse::new(messages, trailers);
+        assert_eq!(response.status, Some(5));
+        assert_eq!(response.message.as_deref(), Some("not%20found"));
+    }
+
+    #[test]
+    fn responses_without_a_status() {
+        let response = GrpcWebResponse::new(vec![], HeaderMap::new());
+
+        assert_eq!(response.status, None);
+        assert_eq!(response.message, None);
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let frame = encode_message(b"message");
+
+        assert_eq!(framing_error(&frame[..3]), "truncated frame header");
+        assert_eq!(
+            framing_error(&frame[..frame.len() - 2]),
+            "the frame declares 7 bytes, but only 5 were received"
+        );
+
+        // A valid frame followed by a truncated one.
+        let body = [&frame[..], &frame[..4]].concat();
+        assert_eq!(framing_error(&body), "truncated frame header");
+    }
+
+    #[test]
+    fn compressed_frames_are_rejected() {
+        let mut frame = encode_message(b"message");
+        frame[0] = COMPRESSED_FRAME;
+
+        assert_eq!(framing_error(&frame), "compressed frames are not supported");
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use reqwest::Method;
+
+use crate::{impit::ErrorType, request::RequestOptions};
+
+/// The headers computed by the client when sending the request, which are not replayed.
+const SKIPPED_HEADERS: &[&str] = &["content-length", "host", "connection", "transfer-encoding"];
+
+/// A request recorded in a HAR (HTTP Archive) file, e.g. exported from the browser DevTools.
+///
+/// Replay it with [`Impit::replay_har_entry`](crate::impit::Impit::replay_har_entry), or pass the fields to [`Impit::build_request`](crate::impit::Impit::build_request).
+#[derive(Debug, Clone)]
+pub struct HarRequest {
+    /// The request method, e.g. `GET`.
+    pub method: Method,
+    /// The full request URL, including the query string.
+    pub url: String,
+    /// The `postData.text` of the request, if any.
+    pub body: Option<Vec<u8>>,
+    /// The options with the recorded headers.
+    pub options: RequestOptions,
+}
+
+impl HarRequest {
+    /// Parses a HAR entry - either the whole entry from `log.entries`, or just its `request` object.
+    ///
+    /// The HTTP/2 pseudo-headers (`:authority`, ...) and the headers the client computes on its own (`Host`, `Content-Length`, ...)
+    /// are skipped. Repeated headers are combined into one (the `Cookie` headers with `; `, others with `, `).
+    ///
+    /// Fails with [`ErrorType::InvalidHarEntry`] if the method or the URL is missing or invalid.
+    ///
+    /// ### Example
+    /// ```rust
+    /// let har: serde_json::Value = serde_json::from_str(&std::fs::read_to_string("capture.har")?)?;
+    ///
+    /// for entry in har["log"]["entries"].as_array().unwrap() {
+    ///     let request = HarRequest::from_entry(entry)?;
+    ///     println!("{} {}", request.method, request.url);
+    /// }
+    /// ```
+    pub fn from_entry(entry: &serde_json::Value) -> Result<Self, ErrorType> {
+        let request = entry.get("request").unwrap_or(entry);
+
+        let method = request["method"]
+            .as_str()
+            .ok_or_else(|| ErrorType::InvalidHarEntry("missing `method`".to_string()))?;
+        let method = Method::from_bytes(method.as_bytes())
+            .map_err(|_| ErrorType::InvalidHarEntry(format!("invalid method `{method}`")))?;
+
+        let url = request["url"]
+            .as_str()
+            .ok_or_else(|| ErrorType::InvalidHarEntry("missing `url`".to_string()))?;
+        url::Url::parse(url)
+            .map_err(|e| ErrorType::InvalidHarEntry(format!("invalid URL `{url}`: {e}")))?;
+
+        let mut headers: HashMap<String, String> = HashMap::new();
+
+        for header in request["headers"].as_array().into_iter().flatten() {
+            let (Some(name), Some(value)) = (header["name"].as_str(), header["value"].as_str())
+            else {
+                continue;
+            };
+
+            let name = name.to_ascii_lowercase();
+            if name.starts_with(':') || SKIPPED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+
+            let separator = if name == "cookie" { "; " } else { ", " };
+            headers
+                .entry(name)
+                .and_modify(|existing| {
+                    existing.push_str(separator);
+                    existing.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+
+        let body = request["postData"]["text"]
+            .as_str()
+            .map(|text| text.as_bytes().to_vec());
+
+        Ok(HarRequest {
+            method,
+            url: url.to_string(),
+            body,
+            options: RequestOptions {
+                headers,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// An entry with the HAR 1.2 fields, shaped like the DevTools exports.
+    fn har_entry() -> serde_json::Value {
+        json!({
+            "startedDateTime": "2025-01-01T12:00:00.000Z",
+            "time": 120.5,
+            "request": {
+                "method": "POST",
+                "url": "https://example.com/api/search?q=shoes&page=2",
+                "httpVersion": "h2",
+                "cookies": [{ "name": "session", "value": "abc" }],
+                "headers": [
+                    { "name": ":authority", "value": "example.com" },
+                    { "name": ":method", "value": "POST" },
+                    { "name": "Accept", "value": "application/json" },
+                    { "name": "Content-Type", "value": "application/json" },
+                    { "name": "Content-Length", "value": "16" },
+                    { "name": "cookie", "value": "session=abc" },
+                    { "name": "Cookie", "value": "theme=dark" },
+                    { "name": "X-Tag", "value": "a" },
+                    { "name": "x-tag", "value": "b" },
+                    { "name": "Host", "value": "example.com" }
+                ],
+                "queryString": [
+                    { "name": "q", "value": "shoes" },
+                    { "name": "page", "value": "2" }
+                ],
+                "postData": {
+                    "mimeType": "application/json",
+                    "text": "{\"query\":\"shoes\"}"
+                },
+                "headersSize": -1,
+                "bodySize": 16
+            },
+            "response": {
+                "status": 200,
+                "statusText": "OK",
+                "httpVersion": "h2",
+                "headers": [],
+                "content": { "size": 0, "mimeType": "application/json" }
+            },
+            "cache": {},
+            "timings": { "send": 0, "wait": 100, "receive": 20.5 }
+        })
+    }
+
+    #[test]
+    fn parses_the_log_entries() {
+        let request = HarRequest::from_entry(&har_entry()).unwrap();
+
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.url, "https://example.com/api/search?q=shoes&page=2");
+        assert_eq!(request.body.as_deref(), Some(&b"{\"query\":\"shoes\"}"[..]));
+
+        let mut headers: Vec<_> = request.options.headers.into_iter().collect();
+        headers.sort();
+        assert_eq!(
+            headers,
+            [
+                ("accept", "application/json"),
+                ("content-type", "application/json"),
+                ("cookie", "session=abc; theme=dark"),
+                ("x-tag", "a, b"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_bare_request_objects() {
+        let entry = har_entry();
+        let request = HarRequest::from_entry(&entry["request"]).unwrap();
+
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.options.headers.len(), 4);
+    }
+
+    #[test]
+    fn requests_without_headers_and_body() {
+        let request =
+            HarRequest::from_entry(&json!({ "method": "GET", "url": "https://example.com/" }))
+                .unwrap();
+
+        assert_eq!(request.method, Method::GET);
+        assert!(request.body.is_none());
+        assert!(request.options.headers.is_empty());
+    }
+
+    #[test]
+    fn rejects_entries_without_a_valid_method_or_url() {
+        for entry in [
+            json!({ "url": "https://example.com/" }),
+            json!({ "method": "GE T", "url": "https://example.com/" }),
+            json!({ "method": "GET" }),
+            json!({ "method": "GET", "url": "/relative" }),
+        ] {
+            assert!(matches!(
+                HarRequest::from_entry(&entry),
+                Err(ErrorType::InvalidHarEntry(_))
+            ));
+        }
+    }
+}
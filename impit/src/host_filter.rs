@@ -0,0 +1,311 @@
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+
+/// Restricts the hosts an [`Impit`](crate::impit::Impit) instance can contact.
+///
+/// The patterns are either exact hostnames (`example.com`) or wildcard suffixes (`*.example.com`), which match all the subdomains.
+/// The matching is case-insensitive.
+#[derive(Debug, Clone, Default)]
+pub struct HostFilter {
+    allowed: Vec<String>,
+    blocked: Vec<String>,
+}
+
+impl HostFilter {
+    pub fn set_allowed(&mut self, patterns: &[&str]) {
+        self.allowed = normalize(patterns);
+    }
+
+    pub fn set_blocked(&mut self, patterns: &[&str]) {
+        self.blocked = normalize(patterns);
+    }
+
+    /// Returns `true` if the host isn't blocked and is allowed (or the allowlist is empty).
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        if self.blocked.iter().any(|pattern| matches(pattern, &host)) {
+            return false;
+        }
+
+        self.allowed.is_empty() || self.allowed.iter().any(|pattern| matches(pattern, &host))
+    }
+}
+
+fn normalize(patterns: &[&str]) -> Vec<String> {
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim().trim_end_matches('.').to_ascii_lowercase())
+        .collect()
+}
+
+fn matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .strip_suffix(suffix)
+            .is_some_and(|subdomain| subdomain.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+/// The error returned from the redirect policy when a redirect leads to a disallowed host.
+#[derive(Debug)]
+pub struct HostNotAllowed(pub String);
+
+impl fmt::Display for HostNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The host `{}` is not allowed.", self.0)
+    }
+}
+
+impl std::error::Error for HostNotAllowed {}
+
+/// The error returned from the redirect policy when a redirect leads from HTTPS to plain HTTP with [`ImpitBuilder::with_https_only`](crate::impit::ImpitBuilder::with_https_only).
+#[derive(Debug)]
+pub struct InsecureRedirect(pub String);
+
+impl fmt::Display for InsecureRedirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The redirect to `{}` downgrades to plain HTTP.", self.0)
+    }
+}
+
+impl std::error::Error for InsecureRedirect {}
+
+/// Returns `true` for the loopback, private (RFC 1918, unique local), link-local, shared (CGNAT), benchmarking, multicast,
+/// IETF protocol assignment and unspecified (`0.0.0.0/8`) addresses.
+///
+/// The IPv4 addresses embedded in IPv6 ones (IPv4-mapped `::ffff:a.b.c.d`, IPv4-compatible `::a.b.c.d` and NAT64 `64:ff9b::a.b.c.d`)
+/// are checked as IPv4 addresses, since they reach the same hosts.
+pub fn is_private_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 0.0.0.0/8
+                || octets[0] == 0
+                // 100.64.0.0/10
+                || (octets[0] == 100 && octets[1] & 0xc0 == 64)
+                // 192.0.0.0/24
+                || (octets[0] == 192 && octets[1] == 0 && octets[2] == 0)
+                // 198.18.0.0/15
+                || (octets[0] == 198 && octets[1] & 0xfe == 18)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+
+            // `to_ipv4` covers both the IPv4-mapped and the IPv4-compatible addresses (`::1` included, as `0.0.0.1`).
+            if let Some(ipv4) = ip.to_ipv4() {
+                return is_private_address(IpAddr::V4(ipv4));
+            }
+
+            // 64:ff9b::/96
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., high, low] = segments;
+                let ipv4 = std::net::Ipv4Addr::from(((high as u32) << 16) | low as u32);
+                return is_private_address(IpAddr::V4(ipv4));
+            }
+
+            ip.is_multicast()
+                // fc00::/7
+                || segments[0] & 0xfe00 == 0xfc00
+                // fe80::/10
+                || segments[0] & 0xffc0 == 0xfe80
+        }
+    }
+}
+
+/// The error returned when a host resolves only to blocked (private) addresses.
+#[derive(Debug)]
+pub struct BlockedAddress(pub String);
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` resolves to a private address.", self.0)
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+/// A DNS resolver that drops the private addresses from the results.
+///
+/// The check happens at connect time (on every resolution), so a DNS rebinding can't slip a private address in after a check.
+pub struct PrivateAddressBlockingResolver;
+
+impl Resolve for PrivateAddressBlockingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_owned();
+            let addrs = lookup_public_addresses(&host).await?;
+
+            Ok::<Addrs, Box<dyn std::error::Error + Send + Sync>>(Box::new(addrs.into_iter()))
+        })
+    }
+}
+
+/// Resolves the host and drops the private addresses. Returns the [`BlockedAddress`] error if there are no public addresses left.
+pub async fn lookup_public_addresses(
+    host: &str,
+) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await?
+        .filter(|addr| !is_private_address(addr.ip()))
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(Box::new(BlockedAddress(host.to_owned())));
+    }
+
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(allowed: &[&str], blocked: &[&str]) -> HostFilter {
+        let mut filter = HostFilter::default();
+        filter.set_allowed(allowed);
+        filter.set_blocked(blocked);
+        filter
+    }
+
+    #[test]
+    fn empty_filter_allows_everything() {
+        assert!(HostFilter::default().allows("example.com"));
+    }
+
+    #[test]
+    fn wildcards_match_only_the_subdomains() {
+        let filter = filter(&["*.example.com"], &[]);
+
+        assert!(filter.allows("www.example.com"));
+        assert!(filter.allows("a.b.example.com"));
+        assert!(!filter.allows("example.com"));
+        assert!(!filter.allows("notexample.com"));
+        assert!(!filter.allows("example.com.evil.com"));
+    }
+
+    #[test]
+    fn exact_patterns_do_not_match_the_subdomains() {
+        let filter = filter(&["example.com"], &[]);
+
+        assert!(filter.allows("example.com"));
+        assert!(!filter.allows("www.example.com"));
+    }
+
+    #[test]
+    fn trailing_dots_and_case_are_normalized() {
+        let filter = filter(&[" Example.COM. ", "*.Example.org"], &[]);
+
+        assert!(filter.allows("example.com"));
+        assert!(filter.allows("EXAMPLE.com."));
+        assert!(filter.allows("WWW.example.ORG."));
+    }
+
+    #[test]
+    fn blocklist_overrides_the_allowlist() {
+        let filter = filter(&["*.example.com"], &["admin.example.com"]);
+
+        assert!(filter.allows("www.example.com"));
+        assert!(!filter.allows("admin.example.com"));
+        assert!(!filter.allows("Admin.Example.com."));
+
+        let filter = filter(&[], &["*.internal"]);
+        assert!(filter.allows("example.com"));
+        assert!(!filter.allows("db.internal"));
+    }
+
+    fn is_private(ip: &str) -> bool {
+        is_private_address(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn private_ipv4_ranges_are_detected() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "100.127.255.254",
+            "0.0.0.0",
+            "0.1.2.3",
+            "192.0.0.8",
+            "198.18.0.1",
+            "198.19.255.255",
+            "224.0.0.1",
+            "239.255.255.250",
+            "255.255.255.255",
+        ] {
+            assert!(is_private(ip), "{ip} should be private");
+        }
+    }
+
+    #[test]
+    fn public_ipv4_addresses_are_allowed() {
+        for ip in [
+            "8.8.8.8",
+            "1.1.1.1",
+            "100.128.0.1",
+            "192.0.1.1",
+            "198.17.255.255",
+            "198.20.0.1",
+            "223.255.255.255",
+        ] {
+            assert!(!is_private(ip), "{ip} should be public");
+        }
+    }
+
+    #[test]
+    fn private_ipv6_ranges_are_detected() {
+        for ip in [
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "::127.0.0.1",
+            "::192.168.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::169.254.169.254",
+        ] {
+            assert!(is_private(ip), "{ip} should be private");
+        }
+    }
+
+    #[test]
+    fn public_ipv6_addresses_are_allowed() {
+        for ip in [
+            "2001:4860:4860::8888",
+            "::ffff:8.8.8.8",
+            "::8.8.8.8",
+            "64:ff9b::808:808",
+        ] {
+            assert!(!is_private(ip), "{ip} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn hosts_resolving_to_private_addresses_are_rejected() {
+        let error = lookup_public_addresses("localhost").await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<BlockedAddress>(),
+            Some(BlockedAddress(host)) if host == "localhost"
+        ));
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use url::{Host, Url};
+
+/// A known HSTS host (RFC 6797).
+struct HstsPolicy {
+    expires: Instant,
+    include_subdomains: bool,
+}
+
+/// Remembers the hosts that sent the `Strict-Transport-Security` header and upgrades the `http://` URLs for them.
+#[derive(Default)]
+pub struct HstsCache {
+    hosts: HashMap<String, HstsPolicy>,
+}
+
+impl HstsCache {
+    /// Processes the `Strict-Transport-Security` header of a response from `url`.
+    ///
+    /// The header is ignored for plain HTTP responses and IP address hosts. `max-age=0` removes the host from the cache.
+    pub fn record(&mut self, url: &Url, header: &str) {
+        let Some(Host::Domain(host)) = url.host() else {
+            return;
+        };
+
+        if url.scheme() != "https" {
+            return;
+        }
+
+        let mut max_age = None;
+        let mut include_subdomains = false;
+
+        for directive in header.split(';') {
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+
+            if name.eq_ignore_ascii_case("max-age") {
+                max_age = value.and_then(|value| value.parse::<u64>().ok());
+            } else if name.eq_ignore_ascii_case("includeSubDomains") {
+                include_subdomains = true;
+            }
+        }
+
+        // The header is invalid without `max-age`.
+        let Some(max_age) = max_age else {
+            return;
+        };
+
+        let host = host.to_ascii_lowercase();
+
+        if max_age == 0 {
+            self.hosts.remove(&host);
+            return;
+        }
+
+        self.hosts.insert(
+            host,
+            HstsPolicy {
+                // Absurdly long max-ages would overflow the `Instant`.
+                expires: Instant::now() + Duration::from_secs(max_age.min(10 * 365 * 24 * 60 * 60)),
+                include_subdomains,
+            },
+        );
+    }
+
+    /// Rewrites an `http://` URL to `https://` if its host (or a parent domain with `includeSubDomains`) is a known HSTS host.
+    ///
+    /// Returns `true` if the URL was upgraded.
+    pub fn upgrade(&self, url: &mut Url) -> bool {
+        let Some(Host::Domain(host)) = url.host() else {
+            return false;
+        };
+
+        if url.scheme() != "http" || !self.is_known_host(&host.to_ascii_lowercase()) {
+            return false;
+        }
+
+        url.set_scheme("https").is_ok()
+    }
+
+    fn is_known_host(&self, host: &str) -> bool {
+        let now = Instant::now();
+
+        let is_active = |domain: &str, subdomain: bool| {
+            self.hosts.get(domain).is_some_and(|policy| {
+                now < policy.expires && (!subdomain || policy.include_subdomains)
+            })
+        };
+
+        if is_active(host, false) {
+            return true;
+        }
+
+        host.match_indices('.')
+            .any(|(i, _)| is_active(&host[i + 1..], true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgraded(cache: &HstsCache, url: &str) -> String {
+        let mut url = Url::parse(url).unwrap();
+        cache.upgrade(&mut url);
+        url.to_string()
+    }
+
+    #[test]
+    fn known_hosts_are_upgraded() {
+        let mut cache = HstsCache::default();
+        cache.record(
+            &Url::parse("https://Example.com/").unwrap(),
+            "max-age=\"3600\"",
+        );
+
+        assert_eq!(
+            upgraded(&cache, "http://example.com:8080/path?q=1"),
+            "https://example.com:8080/path?q=1"
+        );
+        assert_eq!(
+            upgraded(&cache, "http://sub.example.com/"),
+            "http://sub.example.com/"
+        );
+        assert_eq!(upgraded(&cache, "http://other.com/"), "http://other.com/");
+    }
+
+    #[test]
+    fn include_subdomains_covers_the_subdomains() {
+        let mut cache = HstsCache::default();
+        cache.record(
+            &Url::parse("https://example.com/").unwrap(),
+            "max-age=3600; includeSubDomains",
+        );
+
+        assert_eq!(
+            upgraded(&cache, "http://a.b.example.com/"),
+            "https://a.b.example.com/"
+        );
+        assert_eq!(
+            upgraded(&cache, "http://notexample.com/"),
+            "http://notexample.com/"
+        );
+    }
+
+    #[test]
+    fn invalid_and_insecure_headers_are_ignored() {
+        let mut cache = HstsCache::default();
+        cache.record(&Url::parse("http://example.com/").unwrap(), "max-age=3600");
+        cache.record(
+            &Url::parse("https://example.org/").unwrap(),
+            "includeSubDomains",
+        );
+        cache.record(&Url::parse("https://127.0.0.1/").unwrap(), "max-age=3600");
+
+        assert!(cache.hosts.is_empty());
+    }
+
+    #[test]
+    fn max_age_zero_removes_the_host() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut cache = HstsCache::default();
+
+        cache.record(&url, "max-age=3600");
+        cache.record(&url, "max-age=0");
+
+        assert_eq!(
+            upgraded(&cache, "http://example.com/"),
+            "http://example.com/"
+        );
+    }
+
+    #[test]
+    fn expired_policies_are_not_applied() {
+        let mut cache = HstsCache::default();
+        cache.hosts.insert(
+            "example.com".to_owned(),
+            HstsPolicy {
+                expires: Instant::now() - Duration::from_secs(1),
+                include_subdomains: true,
+            },
+        );
+
+        assert_eq!(
+            upgraded(&cache, "http://example.com/"),
+            "http://example.com/"
+        );
+        assert_eq!(
+            upgraded(&cache, "http://sub.example.com/"),
+            "http://sub.example.com/"
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{Duration, Instant};
 
 use hickory_proto::error::ProtoError;
 use hickory_proto::rr::rdata::svcb::SvcParamValue;
@@ -8,7 +10,28 @@ use hickory_client::client::{AsyncClient, ClientHandle};
 use hickory_client::proto::iocompat::AsyncIoTokioAsStd;
 use hickory_client::rr::Name;
 use hickory_client::tcp::TcpClientStream;
-use tokio::net::TcpStream as TokioTcpStream;
+use log::{debug, warn};
+use tokio::net::{lookup_host, TcpStream as TokioTcpStream, UdpSocket};
+
+/// How long to wait for a response to the QUIC reachability probe.
+const QUIC_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+/// How long the QUIC probe result is cached for a host.
+const QUIC_PROBE_TTL: Duration = Duration::from_secs(10 * 60);
+/// The number of hosts failing the QUIC probe (with none passing it) after which UDP is considered blocked on the network.
+const QUIC_BLOCKED_AFTER_HOSTS: usize = 3;
+
+/// How long HTTP/3 isn't attempted for a host after its first failed QUIC connection. Doubles with every consecutive failure.
+const H3_FAILURE_COOLDOWN: Duration = Duration::from_secs(60);
+/// The upper bound for the HTTP/3 failure cooldown.
+const MAX_H3_FAILURE_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// The upper bound for the max age of the `Alt-Svc` entries, which can be arbitrarily large.
+const MAX_ALT_SVC_AGE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// The DNS server used for the HTTP/3 support discovery.
+// todo: use the DNS server from the system config
+pub const DEFAULT_NAME_SERVER: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(8, 8, 8, 8), 53));
 
 /// A struct encapsulating the components required to make HTTP/3 requests.
 pub struct H3Engine {
@@ -18,30 +41,47 @@ pub struct H3Engine {
     bg_join_handle: tokio::task::JoinHandle<Result<(), ProtoError>>,
     /// A map of hosts that support HTTP/3.
     ///
-    /// This is populated by the DNS queries and manual calls to `set_h3_support` (based on the `Alt-Svc` header).
-    /// Implicitly used as a cache for the DNS queries.
-    h3_alt_svc: HashMap<String, bool>,
+    /// This is populated by the DNS queries, manual calls to `set_h3_support` and the `Alt-Svc` headers (`set_h3_alt_svc`).
+    /// Implicitly used as a cache for the DNS queries. The `Alt-Svc` entries expire after their max age.
+    h3_alt_svc: HashMap<String, (bool, Option<Instant>)>,
+    /// The QUIC probe results per host, with their expiration.
+    quic_probes: HashMap<String, (bool, Instant)>,
+    /// Whether QUIC (UDP) traffic is blocked on the network, i.e. several hosts failed the probe.
+    quic_blocked: bool,
+    /// The hosts whose QUIC connections failed - the number of consecutive failures and the end of the cooldown.
+    h3_failures: HashMap<String, (u32, Instant)>,
 }
 
 impl H3Engine {
-    pub async fn init() -> Self {
-        // todo: use the DNS server from the system config
+    /// Creates a new `H3Engine` instance, discovering the HTTP/3 support with the `name_server`.
+    ///
+    /// Returns an error if the DNS client used for the HTTP/3 support discovery couldn't be set up (e.g. due to missing network access).
+    pub async fn init(name_server: SocketAddr) -> Result<Self, ProtoError> {
         let (stream, sender) =
-            TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::new(([8, 8, 8, 8], 53).into());
-        let (client, bg) = AsyncClient::new(stream, sender, None).await.unwrap();
+            TcpClientStream::<AsyncIoTokioAsStd<TokioTcpStream>>::new(name_server);
+        let (client, bg) = AsyncClient::new(stream, sender, None).await?;
 
         let bg_join_handle = tokio::spawn(bg);
 
-        H3Engine {
+        Ok(H3Engine {
             client,
             bg_join_handle,
             h3_alt_svc: HashMap::new(),
-        }
+            quic_probes: HashMap::new(),
+            quic_blocked: false,
+            h3_failures: HashMap::new(),
+        })
     }
 
     pub async fn host_supports_h3(&mut self, host: &String) -> bool {
-        if let Some(supports_h3) = self.h3_alt_svc.get(host) {
-            return supports_h3.to_owned();
+        if matches!(self.h3_failures.get(host), Some((_, until)) if Instant::now() < *until) {
+            return false;
+        }
+
+        match self.h3_alt_svc.get(host) {
+            Some((supports_h3, None)) => return *supports_h3,
+            Some((supports_h3, Some(expires))) if Instant::now() < *expires => return *supports_h3,
+            _ => {}
         }
 
         let domain_name = Name::from_utf8(host).unwrap();
@@ -74,12 +114,135 @@ impl H3Engine {
         dns_h3_support
     }
 
-    pub fn set_h3_support(&mut self, host: &String, supports_h3: bool) {
-        if self.h3_alt_svc.contains_key(host) {
-            return;
+    /// Returns `true` if the host is known to support HTTP/3 and pass the QUIC probe, without any DNS queries or probes.
+    ///
+    /// The hosts without a cached (unexpired) record are reported as not supporting HTTP/3.
+    pub fn cached_h3_support(&self, host: &str) -> bool {
+        let now = Instant::now();
+
+        let in_cooldown = matches!(self.h3_failures.get(host), Some((_, until)) if now < *until);
+        let supports_h3 = match self.h3_alt_svc.get(host) {
+            Some((supports_h3, None)) => *supports_h3,
+            Some((supports_h3, Some(expires))) => *supports_h3 && now < *expires,
+            None => false,
+        };
+        let quic_reachable = !self.quic_blocked
+            && matches!(self.quic_probes.get(host), Some((true, expires)) if now < *expires);
+
+        !in_cooldown && supports_h3 && quic_reachable
+    }
+
+    /// Checks whether QUIC packets get through to the host, by probing it on UDP port 443.
+    ///
+    /// The probe is a padded QUIC packet with a reserved version, to which QUIC servers respond with a Version Negotiation packet (RFC 9000).
+    /// If there's no response within a short timeout, the host is reached over TCP. The results are cached per host (for `QUIC_PROBE_TTL`),
+    /// so the failing HTTP/3 attempts don't stall every request. Once `QUIC_BLOCKED_AFTER_HOSTS` hosts fail the probe (and none passes it),
+    /// UDP is considered blocked on the network and HTTP/3 is disabled for the rest of the session.
+    pub async fn quic_reachable(&mut self, host: &str) -> bool {
+        if self.quic_blocked {
+            return false;
+        }
+
+        if let Some((quic_reachable, expires)) = self.quic_probes.get(host) {
+            if Instant::now() < *expires {
+                return *quic_reachable;
+            }
         }
 
-        self.h3_alt_svc.insert(host.to_owned(), supports_h3);
+        let quic_reachable = Self::probe_quic(host, 443).await;
+        let now = Instant::now();
+        self.quic_probes
+            .insert(host.to_owned(), (quic_reachable, now + QUIC_PROBE_TTL));
+
+        if !quic_reachable {
+            let live_probes: Vec<bool> = self
+                .quic_probes
+                .values()
+                .filter(|(_, expires)| now < *expires)
+                .map(|(reachable, _)| *reachable)
+                .collect();
+            let failed_hosts = live_probes.iter().filter(|reachable| !**reachable).count();
+
+            if failed_hosts >= QUIC_BLOCKED_AFTER_HOSTS && !live_probes.contains(&true) {
+                warn!("QUIC (UDP) traffic seems to be blocked on this network, disabling HTTP/3 for this client.");
+                self.quic_blocked = true;
+            } else {
+                debug!(
+                    "{} didn't respond to the QUIC probe, using TCP for it.",
+                    host
+                );
+            }
+        }
+
+        quic_reachable
+    }
+
+    async fn probe_quic(host: &str, port: u16) -> bool {
+        let probe = async {
+            let addr = lookup_host((host, port)).await?.next().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "no address found")
+            })?;
+
+            let socket = if addr.is_ipv4() {
+                UdpSocket::bind(("0.0.0.0", 0)).await?
+            } else {
+                UdpSocket::bind(("::", 0)).await?
+            };
+            socket.connect(addr).await?;
+
+            // A long header packet with a reserved (`0x?a?a?a?a`) version, an 8-byte destination connection ID
+            // and no source connection ID, padded to the minimum QUIC datagram size.
+            let mut packet = vec![0u8; 1200];
+            packet[0] = 0xc0;
+            packet[1..5].copy_from_slice(&[0x1a, 0x2a, 0x3a, 0x4a]);
+            packet[5] = 8;
+            packet[6..14].copy_from_slice(b"impitprb");
+            socket.send(&packet).await?;
+
+            let mut response = [0u8; 1500];
+            socket.recv(&mut response).await
+        };
+
+        matches!(
+            tokio::time::timeout(QUIC_PROBE_TIMEOUT, probe).await,
+            Ok(Ok(_))
+        )
+    }
+
+    /// Stops using HTTP/3 for the host for a cooldown period, which doubles with every consecutive failure.
+    ///
+    /// Unlike `set_h3_support(host, false)`, this keeps the host's advertised HTTP/3 support, so HTTP/3 is probed again after the cooldown.
+    pub fn record_h3_failure(&mut self, host: &str) {
+        let failures = self
+            .h3_failures
+            .get(host)
+            .map_or(1, |(failures, _)| failures + 1);
+
+        let cooldown = H3_FAILURE_COOLDOWN
+            .saturating_mul(2u32.saturating_pow(failures - 1))
+            .min(MAX_H3_FAILURE_COOLDOWN);
+
+        self.h3_failures
+            .insert(host.to_owned(), (failures, Instant::now() + cooldown));
+    }
+
+    /// Resets the failure backoff after a successful HTTP/3 request.
+    pub fn record_h3_success(&mut self, host: &str) {
+        self.h3_failures.remove(host);
+    }
+
+    /// Updates the cached HTTP/3 support for the given host, overwriting any previous value.
+    pub fn set_h3_support(&mut self, host: &String, supports_h3: bool) {
+        self.h3_alt_svc.insert(host.to_owned(), (supports_h3, None));
+    }
+
+    /// Marks the host as supporting HTTP/3 for the `max_age` of its `Alt-Svc` entry.
+    ///
+    /// After the entry expires, the HTTP/3 support is looked up in the DNS again (until the next `Alt-Svc` header).
+    pub fn set_h3_alt_svc(&mut self, host: &str, max_age: Duration) {
+        let expires = Instant::now() + max_age.min(MAX_ALT_SVC_AGE);
+        self.h3_alt_svc
+            .insert(host.to_owned(), (true, Some(expires)));
     }
 }
 
@@ -88,3 +251,90 @@ impl Drop for H3Engine {
         self.bg_join_handle.abort();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn probe_passes_when_the_server_responds() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let mut packet = [0u8; 1500];
+            let (_, peer) = server.recv_from(&mut packet).await.unwrap();
+            // Any response counts, a real server would send a Version Negotiation packet.
+            server.send_to(&[0x80, 0, 0, 0, 0], peer).await.unwrap();
+        });
+
+        assert!(H3Engine::probe_quic("127.0.0.1", port).await);
+    }
+
+    #[tokio::test]
+    async fn probe_fails_promptly_when_udp_is_dropped() {
+        // Bound, but never responding - like a firewall silently dropping the packets.
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let start = Instant::now();
+        assert!(!H3Engine::probe_quic("127.0.0.1", port).await);
+        assert!(start.elapsed() < QUIC_PROBE_TIMEOUT * 2);
+    }
+
+    /// Returns an engine with a local DNS server, which accepts the connection but never responds.
+    async fn local_engine(name_server: &std::net::TcpListener) -> H3Engine {
+        H3Engine::init(name_server.local_addr().unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn h3_support_is_kept_after_a_failed_connection() {
+        let name_server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut engine = local_engine(&name_server).await;
+        let host = "example.com".to_owned();
+
+        engine.set_h3_alt_svc(&host, Duration::from_secs(60));
+        engine.record_h3_failure(&host);
+        assert!(!engine.host_supports_h3(&host).await);
+
+        // Ends the cooldown, the host is then served from the Alt-Svc cache again.
+        engine.h3_failures.get_mut(&host).unwrap().1 = Instant::now();
+        assert!(engine.host_supports_h3(&host).await);
+    }
+
+    #[tokio::test]
+    async fn cached_h3_support_needs_a_passed_probe() {
+        let name_server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut engine = local_engine(&name_server).await;
+        let host = "example.com".to_owned();
+        assert!(!engine.cached_h3_support(&host));
+
+        engine.set_h3_support(&host, true);
+        assert!(!engine.cached_h3_support(&host));
+
+        engine
+            .quic_probes
+            .insert(host.clone(), (true, Instant::now() + QUIC_PROBE_TTL));
+        assert!(engine.cached_h3_support(&host));
+
+        engine.record_h3_failure(&host);
+        assert!(!engine.cached_h3_support(&host));
+    }
+
+    #[tokio::test]
+    async fn failure_cooldown_doubles_and_resets_on_success() {
+        let name_server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut engine = local_engine(&name_server).await;
+
+        engine.record_h3_failure("example.com");
+        engine.record_h3_failure("example.com");
+        let (failures, until) = engine.h3_failures["example.com"];
+        assert_eq!(failures, 2);
+        assert!(until > Instant::now() + H3_FAILURE_COOLDOWN);
+
+        engine.record_h3_success("example.com");
+        assert!(!engine.h3_failures.contains_key("example.com"));
+    }
+}
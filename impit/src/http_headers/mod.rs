@@ -1,16 +1,88 @@
-use crate::emulation::Browser;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use std::{collections::HashMap, str::FromStr};
+use crate::{
+    emulation::{Browser, ProfileInfo},
+    request::{ResourceType, SecFetch},
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONNECTION, HOST};
+use std::{collections::HashMap, str::FromStr, sync::OnceLock};
 
 mod statics;
 
+pub fn profile_info(browser: Browser) -> ProfileInfo {
+    let (headers, version) = match browser {
+        Browser::Chrome => (statics::CHROME_HEADERS, statics::CHROME_VERSION),
+        Browser::Firefox => (statics::FIREFOX_HEADERS, statics::FIREFOX_VERSION),
+    };
+
+    let user_agent = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+        .map(|(_, value)| *value)
+        .unwrap_or_default();
+
+    ProfileInfo {
+        user_agent,
+        version,
+        platform: statics::PROFILE_PLATFORM,
+    }
+}
+
+#[cfg(feature = "websocket")]
 pub fn chrome_websocket_headers() -> HashMap<String, String> {
     statics::CHROME_SOCKET_HEADERS
         .iter()
-        .map(|&(k, v)| (k.to_string(), v.to_string()))
+        .map(|&(k, v)| {
+            let v = if k.eq_ignore_ascii_case("accept-encoding") {
+                decodable_accept_encoding(v, statics::DECODED_CONTENT_ENCODINGS)
+            } else {
+                v.to_string()
+            };
+
+            (k.to_string(), v)
+        })
         .collect()
 }
 
+/// Returns the HTTP/2 pseudo-header order of the given browser. Empty for the vanilla (`None`) client.
+pub fn pseudo_headers_order(browser: Option<Browser>) -> &'static [&'static str] {
+    match browser {
+        Some(Browser::Chrome) => statics::CHROME_PSEUDOHEADERS_ORDER.as_ref(),
+        Some(Browser::Firefox) => statics::FIREFOX_PSEUDOHEADERS_ORDER.as_ref(),
+        None => &[],
+    }
+}
+
+/// The HTTP/2 pseudo-header order of the process, see [`set_pseudo_headers_order`].
+static PROCESS_PSEUDO_HEADERS_ORDER: OnceLock<&'static [&'static str]> = OnceLock::new();
+
+/// Sets the HTTP/2 pseudo-header order of `browser` for the whole process and returns the order in effect.
+///
+/// The patched `h2` reads the order from the `IMPIT_H2_PSEUDOHEADERS_ORDER` environment variable whenever it encodes
+/// the request headers (on the connection tasks), so changing the variable while other requests are in flight would race.
+/// It's only written once, by the first impersonating client - the returned order differs from the one of `browser`
+/// if a client impersonating another browser was created before. The vanilla (`None`) client doesn't set any order.
+pub fn set_pseudo_headers_order(browser: Option<Browser>) -> &'static [&'static str] {
+    let order = pseudo_headers_order(browser);
+    if order.is_empty() {
+        return sent_pseudo_headers_order(browser);
+    }
+
+    PROCESS_PSEUDO_HEADERS_ORDER.get_or_init(|| {
+        std::env::set_var("IMPIT_H2_PSEUDOHEADERS_ORDER", order.join(","));
+        order
+    })
+}
+
+/// Returns the HTTP/2 pseudo-header order the requests impersonating `browser` are sent with.
+///
+/// That's the process-wide order (see [`set_pseudo_headers_order`]), or the one of `browser` if no order was set yet.
+/// Empty if `h2` uses its default order.
+pub fn sent_pseudo_headers_order(browser: Option<Browser>) -> &'static [&'static str] {
+    PROCESS_PSEUDO_HEADERS_ORDER
+        .get()
+        .copied()
+        .unwrap_or_else(|| pseudo_headers_order(browser))
+}
+
 pub struct HttpHeaders {
     context: HttpHeadersBuilder,
 }
@@ -37,29 +109,192 @@ impl From<HttpHeaders> for HeaderMap {
             None => &[],
         };
 
-        let pseudo_headers_order: &[&str] = match val.context.browser {
-            Some(Browser::Chrome) => statics::CHROME_PSEUDOHEADERS_ORDER.as_ref(),
-            Some(Browser::Firefox) => statics::FIREFOX_PSEUDOHEADERS_ORDER.as_ref(),
+        let header_variants = match val.context.browser {
+            Some(Browser::Chrome) => statics::CHROME_HEADER_VARIANTS,
+            Some(Browser::Firefox) => statics::FIREFOX_HEADER_VARIANTS,
             None => &[],
         };
 
-        if !pseudo_headers_order.is_empty() {
-            std::env::set_var(
-                "IMPIT_H2_PSEUDOHEADERS_ORDER",
-                pseudo_headers_order.join(","),
-            );
+        let header_variant: &[(&str, &str)] = match val.context.profile_jitter {
+            Some(seed) if !header_variants.is_empty() => {
+                header_variants[(mix_seed(seed) % header_variants.len() as u64) as usize]
+            }
+            _ => &[],
+        };
+
+        let destination_priorities = match val.context.browser {
+            Some(Browser::Chrome) => statics::CHROME_PRIORITIES,
+            Some(Browser::Firefox) => statics::FIREFOX_PRIORITIES,
+            None => &[],
+        };
+
+        // The priority the browser would send for the `Sec-Fetch-Dest` destination, an explicit one wins over it (see below).
+        let destination_priority = val.context.sec_fetch.as_ref().and_then(|sec_fetch| {
+            destination_priorities
+                .iter()
+                .find(|(destination, _)| destination.eq_ignore_ascii_case(&sec_fetch.dest))
+                .map(|(_, priority)| *priority)
+        });
+
+        let accept_headers = match val.context.browser {
+            Some(Browser::Chrome) => statics::CHROME_ACCEPT_HEADERS,
+            Some(Browser::Firefox) => statics::FIREFOX_ACCEPT_HEADERS,
+            None => &[],
+        };
+
+        let resource_accept = val.context.resource_type.and_then(|resource_type| {
+            accept_headers
+                .iter()
+                .find(|(destination, _)| *destination == resource_type.as_str())
+                .map(|(_, accept)| *accept)
+        });
+
+        if let Some(host_header) = &val.context.host_header {
+            headers.append(HOST, host_header.clone());
         }
 
         let mut used_custom_headers: Vec<String> = vec![];
 
+        let connection_header_before = match val.context.browser {
+            Some(Browser::Chrome) => statics::CHROME_CONNECTION_HEADER_BEFORE,
+            Some(Browser::Firefox) => statics::FIREFOX_CONNECTION_HEADER_BEFORE,
+            None => "",
+        };
+
+        // Plain HTTP requests always go over HTTP/1.1, where the browsers ask for a persistent connection explicitly.
+        // HTTPS requests mostly negotiate HTTP/2, which forbids the header. A custom `Connection` header always wins.
+        let custom_connection = val
+            .context
+            .custom_headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case("connection"));
+
+        let mut connection = if custom_connection {
+            None
+        } else if val.context.connection_close {
+            Some("close")
+        } else if !val.context.https && val.context.browser.is_some() {
+            Some("keep-alive")
+        } else {
+            None
+        };
+
+        let privacy_headers_before = match val.context.browser {
+            Some(Browser::Chrome) => statics::CHROME_PRIVACY_HEADERS_BEFORE,
+            Some(Browser::Firefox) => statics::FIREFOX_PRIVACY_HEADERS_BEFORE,
+            None => "",
+        };
+
+        // The custom `DNT` and `Sec-GPC` headers win, the same way as the custom `Connection` header.
+        let mut privacy_headers: Vec<&str> = [
+            ("dnt", val.context.do_not_track),
+            ("sec-gpc", val.context.global_privacy_control),
+        ]
+        .into_iter()
+        .filter(|(name, enabled)| {
+            *enabled
+                && !val
+                    .context
+                    .custom_headers
+                    .keys()
+                    .any(|custom_name| custom_name.eq_ignore_ascii_case(name))
+        })
+        .map(|(name, _)| name)
+        .collect();
+
         // TODO: don't use HTTP2 headers for HTTP1.1
         for (name, impersonated_value) in header_values {
-            let value: &str = match val.context.custom_headers.get(*name) {
-                Some(custom_value) => {
-                    used_custom_headers.push(name.to_string());
+            if val.context.without_accept_encoding && name.eq_ignore_ascii_case("accept-encoding") {
+                continue;
+            }
+
+            if name.eq_ignore_ascii_case(privacy_headers_before) {
+                for privacy_header in privacy_headers.drain(..) {
+                    headers.append(privacy_header, HeaderValue::from_static("1"));
+                }
+            }
+
+            if name.eq_ignore_ascii_case(connection_header_before) {
+                if let Some(connection) = connection.take() {
+                    headers.append(CONNECTION, HeaderValue::from_static(connection));
+                }
+            }
+
+            let impersonated_value = header_variant
+                .iter()
+                .find(|(variant_name, _)| variant_name == name)
+                .map(|(_, variant_value)| variant_value)
+                .unwrap_or(impersonated_value);
+
+            // The product tokens are appended at the end, the same way e.g. Edge or Opera extend the Chrome user agent.
+            let suffixed_user_agent = match &val.context.user_agent_suffix {
+                Some(suffix) if name.eq_ignore_ascii_case("user-agent") => {
+                    format!("{} {}", impersonated_value, suffix)
+                }
+                _ => impersonated_value.to_string(),
+            };
+            let accept_encoding = name.eq_ignore_ascii_case("accept-encoding").then(|| {
+                decodable_accept_encoding(impersonated_value, statics::DECODED_CONTENT_ENCODINGS)
+            });
+            let impersonated_value = match (&val.context.accept_language, &accept_encoding) {
+                (Some(accept_language), _) if name.eq_ignore_ascii_case("accept-language") => {
+                    accept_language.as_str()
+                }
+                (_, Some(accept_encoding)) => accept_encoding.as_str(),
+                _ => suffixed_user_agent.as_str(),
+            };
+
+            // Header names are case-insensitive, so e.g. `accept` overrides Firefox's `Accept`.
+            let custom_header = val
+                .context
+                .custom_headers
+                .iter()
+                .find(|(custom_name, _)| custom_name.eq_ignore_ascii_case(name));
+
+            let sec_fetch_value = val.context.sec_fetch.as_ref().and_then(|sec_fetch| {
+                match name.to_ascii_lowercase().as_str() {
+                    "sec-fetch-site" => Some(Some(sec_fetch.site.as_str())),
+                    "sec-fetch-mode" => Some(Some(sec_fetch.mode.as_str())),
+                    "sec-fetch-dest" => Some(Some(sec_fetch.dest.as_str())),
+                    "sec-fetch-user" if sec_fetch.user => Some(Some("?1")),
+                    "sec-fetch-user" => Some(None),
+                    _ => None,
+                }
+            });
+
+            let is_subresource = val
+                .context
+                .resource_type
+                .is_some_and(|resource_type| resource_type != ResourceType::Document);
+
+            if let (Some(priority), None) = (&val.context.priority, custom_header) {
+                if name.eq_ignore_ascii_case("priority") {
+                    headers.append(HeaderName::from_static("priority"), priority.clone());
+                    continue;
+                }
+            }
+
+            let sec_fetch_value = match destination_priority {
+                Some(priority) if name.eq_ignore_ascii_case("priority") => Some(Some(priority)),
+                _ if name.eq_ignore_ascii_case("accept") && resource_accept.is_some() => {
+                    Some(resource_accept)
+                }
+                // Only the navigations ask for the HTTPS upgrade.
+                _ if name.eq_ignore_ascii_case("upgrade-insecure-requests") && is_subresource => {
+                    Some(None)
+                }
+                _ => sec_fetch_value,
+            };
+
+            let value: &str = match (custom_header, sec_fetch_value) {
+                (Some((custom_name, custom_value)), _) => {
+                    used_custom_headers.push(custom_name.to_string());
                     custom_value.as_str()
                 }
-                None => impersonated_value,
+                (None, Some(Some(sec_fetch_value))) => sec_fetch_value,
+                // The header is omitted, e.g. `Sec-Fetch-User` for requests without user activation.
+                (None, Some(None)) => continue,
+                (None, None) => impersonated_value,
             };
 
             headers.append(
@@ -68,8 +303,31 @@ impl From<HttpHeaders> for HeaderMap {
             );
         }
 
+        if let Some(connection) = connection {
+            headers.append(CONNECTION, HeaderValue::from_static(connection));
+        }
+
+        for privacy_header in privacy_headers {
+            headers.append(privacy_header, HeaderValue::from_static("1"));
+        }
+
+        // The vanilla client has no `priority` header in its profile to replace.
+        let has_priority = headers.contains_key("priority")
+            || val
+                .context
+                .custom_headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("priority"));
+
+        if let (Some(priority), false) = (&val.context.priority, has_priority) {
+            headers.append(HeaderName::from_static("priority"), priority.clone());
+        }
+
         val.context.custom_headers.iter().for_each(|(name, value)| {
-            if !used_custom_headers.contains(name) {
+            let omitted =
+                val.context.without_accept_encoding && name.eq_ignore_ascii_case("accept-encoding");
+
+            if !used_custom_headers.contains(name) && !omitted {
                 headers.append(
                     HeaderName::from_str(name).unwrap(),
                     HeaderValue::from_str(value).unwrap(),
@@ -81,12 +339,54 @@ impl From<HttpHeaders> for HeaderMap {
     }
 }
 
+/// Keeps only the encodings from the `accept-encoding` header `value` that are in `decoded` (and the `identity` / `*` values),
+/// so that the servers don't send compressed responses `impit` can't read. Returns `identity` if none is left.
+fn decodable_accept_encoding(value: &str, decoded: &[&str]) -> String {
+    let encodings: Vec<&str> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|encoding| {
+            let name = encoding.split(';').next().unwrap_or_default().trim();
+
+            ["identity", "*"]
+                .iter()
+                .chain(decoded)
+                .any(|decoded| decoded.eq_ignore_ascii_case(name))
+        })
+        .collect();
+
+    if encodings.is_empty() {
+        "identity".to_owned()
+    } else {
+        encodings.join(", ")
+    }
+}
+
+/// Scrambles the jitter seed (SplitMix64 finalizer), so that consecutive seeds don't map to consecutive variants.
+fn mix_seed(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
 #[derive(Default, Clone)]
 pub struct HttpHeadersBuilder {
     host: String,
+    host_header: Option<HeaderValue>,
     browser: Option<Browser>,
     https: bool,
     custom_headers: HashMap<String, String>,
+    sec_fetch: Option<SecFetch>,
+    profile_jitter: Option<u64>,
+    user_agent_suffix: Option<String>,
+    accept_language: Option<String>,
+    connection_close: bool,
+    priority: Option<HeaderValue>,
+    resource_type: Option<ResourceType>,
+    do_not_track: bool,
+    global_privacy_control: bool,
+    without_accept_encoding: bool,
 }
 
 impl HttpHeadersBuilder {
@@ -96,6 +396,12 @@ impl HttpHeadersBuilder {
         self
     }
 
+    /// Emits an explicit `Host` header (as the first header), instead of letting the HTTP client derive it from the URL.
+    pub fn with_host_header(&mut self, host_header: Option<HeaderValue>) -> &mut Self {
+        self.host_header = host_header;
+        self
+    }
+
     pub fn with_browser(&mut self, browser: &Option<Browser>) -> &mut Self {
         self.browser = browser.to_owned();
         self
@@ -111,7 +417,525 @@ impl HttpHeadersBuilder {
         self
     }
 
+    pub fn with_sec_fetch(&mut self, sec_fetch: &Option<SecFetch>) -> &mut Self {
+        self.sec_fetch = sec_fetch.to_owned();
+        self
+    }
+
+    /// Picks one of the plausible variants of the browser profile (e.g. a different minor browser version), based on the seed.
+    /// The same seed always results in the same variant.
+    pub fn with_profile_jitter(&mut self, seed: Option<u64>) -> &mut Self {
+        self.profile_jitter = seed;
+        self
+    }
+
+    /// Appends the product token(s) to the impersonated `User-Agent` header.
+    pub fn with_user_agent_suffix(&mut self, suffix: &Option<String>) -> &mut Self {
+        self.user_agent_suffix = suffix.to_owned();
+        self
+    }
+
+    /// Replaces the impersonated `Accept-Language` header value, keeping the header in its position.
+    pub fn with_accept_language(&mut self, accept_language: &Option<String>) -> &mut Self {
+        self.accept_language = accept_language.to_owned();
+        self
+    }
+
+    pub fn with_connection_close(&mut self, connection_close: bool) -> &mut Self {
+        self.connection_close = connection_close;
+        self
+    }
+
+    /// Sets the `priority` header value, replacing the profile default in its original position.
+    pub fn with_priority(&mut self, priority: &Option<HeaderValue>) -> &mut Self {
+        self.priority = priority.to_owned();
+        self
+    }
+
+    /// Sets the `accept` header for the resource type (the `Sec-Fetch-*` headers are set with [`HttpHeadersBuilder::with_sec_fetch`]).
+    pub fn with_resource_type(&mut self, resource_type: Option<ResourceType>) -> &mut Self {
+        self.resource_type = resource_type;
+        self
+    }
+
+    /// Adds the `DNT: 1` header.
+    pub fn with_do_not_track(&mut self, do_not_track: bool) -> &mut Self {
+        self.do_not_track = do_not_track;
+        self
+    }
+
+    /// Adds the `Sec-GPC: 1` header.
+    pub fn with_global_privacy_control(&mut self, global_privacy_control: bool) -> &mut Self {
+        self.global_privacy_control = global_privacy_control;
+        self
+    }
+
+    /// Omits the `Accept-Encoding` header, including a custom one.
+    pub fn without_accept_encoding(&mut self, without_accept_encoding: bool) -> &mut Self {
+        self.without_accept_encoding = without_accept_encoding;
+        self
+    }
+
     pub fn build(&self) -> HttpHeaders {
         HttpHeaders::new(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn pseudo_headers_order_is_set_once_per_process() {
+        // Other tests might have created a client first, so only the consistency is checked.
+        let order = set_pseudo_headers_order(Some(Browser::Firefox));
+        assert!(!order.is_empty());
+        assert_eq!(set_pseudo_headers_order(Some(Browser::Chrome)), order);
+        assert_eq!(set_pseudo_headers_order(None), order);
+        assert_eq!(sent_pseudo_headers_order(Some(Browser::Chrome)), order);
+        assert_eq!(sent_pseudo_headers_order(None), order);
+        assert_eq!(
+            std::env::var("IMPIT_H2_PSEUDOHEADERS_ORDER").unwrap(),
+            order.join(",")
+        );
+    }
+
+    /// Builds the HTTPS request headers of `browser`, with the builder customized by `configure`.
+    fn build_headers(
+        browser: Option<Browser>,
+        configure: impl FnOnce(&mut HttpHeadersBuilder),
+    ) -> HeaderMap {
+        let mut builder = HttpHeaders::get_builder();
+        builder
+            .with_browser(&browser)
+            .with_host("example.com")
+            .with_https(true);
+        configure(&mut builder);
+
+        builder.build().into()
+    }
+
+    fn header_names(headers: &HeaderMap) -> Vec<&str> {
+        headers.keys().map(HeaderName::as_str).collect()
+    }
+
+    fn sec_fetch(site: &str, mode: &str, dest: &str, user: bool) -> Option<SecFetch> {
+        Some(SecFetch {
+            site: site.to_string(),
+            mode: mode.to_string(),
+            dest: dest.to_string(),
+            user,
+        })
+    }
+
+    #[test]
+    fn sec_fetch_values_replace_the_profile_defaults_in_place() {
+        let defaults = build_headers(Some(Browser::Chrome), |_| {});
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder.with_sec_fetch(&sec_fetch("same-origin", "cors", "empty", false));
+        });
+
+        assert_eq!(headers["sec-fetch-site"], "same-origin");
+        assert_eq!(headers["sec-fetch-mode"], "cors");
+        assert_eq!(headers["sec-fetch-dest"], "empty");
+
+        // Without user activation, `Sec-Fetch-User` is omitted and the other headers keep their positions.
+        let expected: Vec<&str> = header_names(&defaults)
+            .into_iter()
+            .filter(|name| *name != "sec-fetch-user")
+            .collect();
+        assert_eq!(header_names(&headers), expected);
+
+        let headers = build_headers(Some(Browser::Firefox), |builder| {
+            builder.with_sec_fetch(&sec_fetch("cross-site", "navigate", "iframe", true));
+        });
+        assert_eq!(headers["sec-fetch-site"], "cross-site");
+        assert_eq!(headers["sec-fetch-user"], "?1");
+    }
+
+    #[test]
+    fn custom_headers_win_over_the_sec_fetch_values() {
+        let custom_headers = HashMap::from([("Sec-Fetch-Mode".to_string(), "no-cors".to_string())]);
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder
+                .with_sec_fetch(&sec_fetch("same-origin", "cors", "empty", false))
+                .with_custom_headers(&custom_headers);
+        });
+
+        assert_eq!(headers["sec-fetch-mode"], "no-cors");
+        assert_eq!(headers.get_all("sec-fetch-mode").iter().count(), 1);
+        assert_eq!(headers["sec-fetch-site"], "same-origin");
+    }
+
+    #[test]
+    fn profile_jitter_picks_a_consistent_variant() {
+        for browser in [Browser::Chrome, Browser::Firefox] {
+            let canonical = build_headers(Some(browser), |_| {});
+            let jittered = |seed| {
+                build_headers(Some(browser), |builder| {
+                    builder.with_profile_jitter(Some(seed));
+                })
+            };
+
+            let mut user_agents = HashSet::new();
+            for seed in 0..256 {
+                let headers = jittered(seed);
+                assert_eq!(headers, jittered(seed));
+                // Only the values change, the headers and their order are the ones of the profile.
+                assert_eq!(header_names(&headers), header_names(&canonical));
+
+                user_agents.insert(headers["user-agent"].to_str().unwrap().to_owned());
+            }
+
+            let variants = match browser {
+                Browser::Chrome => statics::CHROME_HEADER_VARIANTS,
+                Browser::Firefox => statics::FIREFOX_HEADER_VARIANTS,
+            };
+            let expected_user_agents: HashSet<String> = variants
+                .iter()
+                .map(|variant| {
+                    variant
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+                        .map(|(_, value)| value.to_string())
+                        .unwrap_or_else(|| canonical["user-agent"].to_str().unwrap().to_owned())
+                })
+                .collect();
+            assert_eq!(user_agents, expected_user_agents);
+        }
+    }
+
+    #[test]
+    fn profile_jitter_is_ignored_by_the_vanilla_client() {
+        let headers = build_headers(None, |builder| {
+            builder.with_profile_jitter(Some(3));
+        });
+
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn user_agent_suffix_is_appended_to_the_profile_user_agent() {
+        let suffix = Some("MyCrawler/1.0 (+https://example.com/bot)".to_string());
+
+        for browser in [Browser::Chrome, Browser::Firefox] {
+            let canonical = build_headers(Some(browser), |_| {});
+            let headers = build_headers(Some(browser), |builder| {
+                builder.with_user_agent_suffix(&suffix);
+            });
+
+            assert_eq!(
+                headers["user-agent"].to_str().unwrap(),
+                format!(
+                    "{} MyCrawler/1.0 (+https://example.com/bot)",
+                    canonical["user-agent"].to_str().unwrap()
+                )
+            );
+            assert_eq!(header_names(&headers), header_names(&canonical));
+            assert_eq!(headers.get("accept"), canonical.get("accept"));
+        }
+    }
+
+    #[test]
+    fn custom_user_agents_are_sent_without_the_suffix() {
+        let custom_headers = HashMap::from([("User-Agent".to_string(), "custom".to_string())]);
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder
+                .with_user_agent_suffix(&Some("MyCrawler/1.0".to_string()))
+                .with_custom_headers(&custom_headers);
+        });
+
+        assert_eq!(headers["user-agent"], "custom");
+    }
+
+    fn accept_encoding(browser: Browser) -> String {
+        let headers: HeaderMap = HttpHeaders::get_builder()
+            .with_browser(&Some(browser))
+            .with_host("example.com")
+            .with_https(true)
+            .build()
+            .into();
+
+        headers["accept-encoding"].to_str().unwrap().to_owned()
+    }
+
+    #[cfg(all(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    #[test]
+    fn profiles_advertise_their_encodings() {
+        assert_eq!(accept_encoding(Browser::Chrome), "gzip, deflate, br, zstd");
+        assert_eq!(accept_encoding(Browser::Firefox), "gzip, deflate, br, zstd");
+    }
+
+    #[test]
+    fn accept_encoding_can_be_omitted() {
+        let custom_headers = HashMap::from([("Accept-Encoding".to_string(), "gzip".to_string())]);
+
+        for browser in [Some(Browser::Chrome), Some(Browser::Firefox), None] {
+            let headers: HeaderMap = HttpHeaders::get_builder()
+                .with_browser(&browser)
+                .with_host("example.com")
+                .with_https(true)
+                .with_custom_headers(&custom_headers)
+                .without_accept_encoding(true)
+                .build()
+                .into();
+
+            assert!(!headers.contains_key("accept-encoding"));
+            assert!(browser.is_none() || headers.contains_key("user-agent"));
+        }
+    }
+
+    #[test]
+    fn undecodable_encodings_are_dropped() {
+        assert_eq!(
+            decodable_accept_encoding("gzip;q=1.0, br, identity, *;q=0", &["gzip", "br"]),
+            "gzip;q=1.0, br, identity, *;q=0"
+        );
+        assert_eq!(
+            decodable_accept_encoding("gzip, deflate, br, zstd", &["gzip", "zstd"]),
+            "gzip, zstd"
+        );
+        assert_eq!(decodable_accept_encoding("GZIP, dcb", &["gzip"]), "GZIP");
+        assert_eq!(
+            decodable_accept_encoding("gzip, deflate, br, zstd", &[]),
+            "identity"
+        );
+    }
+
+    #[test]
+    fn profiles_advertise_only_the_decoded_encodings() {
+        for browser in [Browser::Chrome, Browser::Firefox] {
+            let accept_encoding = accept_encoding(browser);
+
+            for encoding in accept_encoding.split(", ") {
+                assert!(
+                    encoding == "identity"
+                        || statics::DECODED_CONTENT_ENCODINGS.contains(&encoding),
+                    "{encoding} can't be decoded"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn all_browsers_are_listed_once() {
+        let browsers = Browser::all();
+        let unique: HashSet<_> = browsers.iter().map(|b| format!("{b:?}")).collect();
+
+        assert_eq!(unique.len(), browsers.len());
+        assert!(browsers.contains(&Browser::Chrome));
+        assert!(browsers.contains(&Browser::Firefox));
+    }
+
+    #[test]
+    fn profile_info_matches_the_sent_headers() {
+        for browser in Browser::all() {
+            let info = crate::emulation::profile_info(*browser);
+            let headers = build_headers(Some(*browser), |_| {});
+
+            assert_eq!(headers["user-agent"], info.user_agent, "{browser:?}");
+            assert!(info.user_agent.contains(info.platform), "{browser:?}");
+        }
+
+        let chrome = crate::emulation::profile_info(Browser::Chrome);
+        assert_eq!(chrome.version, 125);
+        assert!(chrome.user_agent.contains("Chrome/125."));
+
+        let firefox = crate::emulation::profile_info(Browser::Firefox);
+        assert_eq!(firefox.version, 128);
+        assert!(firefox.user_agent.contains("Firefox/128."));
+    }
+
+    /// Returns the `Connection` header value and the name of the header right after it.
+    fn connection_header(headers: &HeaderMap) -> Option<(&str, Option<&str>)> {
+        let names = header_names(headers);
+        let position = names.iter().position(|name| *name == "connection")?;
+
+        Some((
+            headers["connection"].to_str().unwrap(),
+            names.get(position + 1).copied(),
+        ))
+    }
+
+    #[test]
+    fn plain_http_requests_ask_for_keep_alive_in_the_browser_position() {
+        let plain = |browser| {
+            build_headers(Some(browser), |builder| {
+                builder.with_https(false);
+            })
+        };
+
+        assert_eq!(
+            connection_header(&plain(Browser::Chrome)),
+            Some(("keep-alive", Some("sec-ch-ua")))
+        );
+        assert_eq!(
+            connection_header(&plain(Browser::Firefox)),
+            Some(("keep-alive", Some("sec-fetch-dest")))
+        );
+    }
+
+    #[test]
+    fn https_and_vanilla_requests_have_no_connection_header() {
+        assert_eq!(
+            connection_header(&build_headers(Some(Browser::Chrome), |_| {})),
+            None
+        );
+        assert_eq!(
+            connection_header(&build_headers(None, |builder| {
+                builder.with_https(false);
+            })),
+            None
+        );
+    }
+
+    #[test]
+    fn connection_close_replaces_keep_alive() {
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder.with_https(false).with_connection_close(true);
+        });
+        assert_eq!(
+            connection_header(&headers),
+            Some(("close", Some("sec-ch-ua")))
+        );
+
+        // Closing the connection is up to the caller on HTTPS (and the vanilla client) as well.
+        let headers = build_headers(None, |builder| {
+            builder.with_connection_close(true);
+        });
+        assert_eq!(connection_header(&headers), Some(("close", None)));
+    }
+
+    #[test]
+    fn custom_connection_headers_win() {
+        let custom_headers = HashMap::from([("Connection".to_string(), "upgrade".to_string())]);
+        let headers = build_headers(Some(Browser::Firefox), |builder| {
+            builder
+                .with_https(false)
+                .with_connection_close(true)
+                .with_custom_headers(&custom_headers);
+        });
+
+        assert_eq!(
+            headers.get_all("connection").iter().collect::<Vec<_>>(),
+            ["upgrade"]
+        );
+    }
+
+    #[test]
+    fn priorities_follow_the_destination() {
+        let script = |browser| {
+            build_headers(Some(browser), |builder| {
+                builder.with_sec_fetch(&sec_fetch("same-origin", "no-cors", "script", false));
+            })
+        };
+
+        assert_eq!(
+            build_headers(Some(Browser::Chrome), |_| {})["priority"],
+            "u=0, i"
+        );
+        assert_eq!(script(Browser::Chrome)["priority"], "u=1");
+        assert_eq!(script(Browser::Firefox)["priority"], "u=2");
+    }
+
+    #[test]
+    fn explicit_priorities_win_and_keep_the_header_position() {
+        let defaults = build_headers(Some(Browser::Chrome), |_| {});
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder.with_priority(&Some(HeaderValue::from_static("u=3")));
+        });
+        assert_eq!(
+            headers.get_all("priority").iter().collect::<Vec<_>>(),
+            ["u=3"]
+        );
+        assert_eq!(header_names(&headers), header_names(&defaults));
+
+        let headers = build_headers(Some(Browser::Firefox), |builder| {
+            builder
+                .with_sec_fetch(&sec_fetch("same-origin", "no-cors", "script", false))
+                .with_priority(&Some(HeaderValue::from_static("u=3")));
+        });
+        assert_eq!(
+            headers.get_all("priority").iter().collect::<Vec<_>>(),
+            ["u=3"]
+        );
+    }
+
+    #[test]
+    fn vanilla_requests_get_the_explicit_priority_only() {
+        assert!(!build_headers(None, |_| {}).contains_key("priority"));
+
+        let headers = build_headers(None, |builder| {
+            builder.with_priority(&Some(HeaderValue::from_static("u=1, i")));
+        });
+        assert_eq!(headers["priority"], "u=1, i");
+    }
+
+    /// Returns the header names right before `name`.
+    fn headers_before<'a>(headers: &'a HeaderMap, name: &str, count: usize) -> Vec<&'a str> {
+        let names = header_names(headers);
+        let position = names.iter().position(|n| *n == name).unwrap();
+
+        names[position.saturating_sub(count)..position].to_vec()
+    }
+
+    #[test]
+    fn privacy_headers_are_inserted_in_the_browser_position() {
+        for (browser, before) in [
+            (Browser::Chrome, "sec-fetch-site"),
+            (Browser::Firefox, "sec-fetch-dest"),
+        ] {
+            let headers = build_headers(Some(browser), |builder| {
+                builder
+                    .with_do_not_track(true)
+                    .with_global_privacy_control(true);
+            });
+
+            assert_eq!(headers["dnt"], "1");
+            assert_eq!(headers["sec-gpc"], "1");
+            assert_eq!(headers_before(&headers, before, 2), ["dnt", "sec-gpc"]);
+        }
+    }
+
+    #[test]
+    fn privacy_headers_are_omitted_by_default() {
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder.with_global_privacy_control(true);
+        });
+        assert!(!headers.contains_key("dnt"));
+        assert_eq!(headers_before(&headers, "sec-fetch-site", 1), ["sec-gpc"]);
+
+        let headers = build_headers(Some(Browser::Firefox), |_| {});
+        assert!(!headers.contains_key("dnt"));
+        assert!(!headers.contains_key("sec-gpc"));
+    }
+
+    #[test]
+    fn custom_privacy_headers_win() {
+        let custom_headers = HashMap::from([("DNT".to_string(), "0".to_string())]);
+        let headers = build_headers(Some(Browser::Chrome), |builder| {
+            builder
+                .with_do_not_track(true)
+                .with_custom_headers(&custom_headers);
+        });
+
+        assert_eq!(headers.get_all("dnt").iter().collect::<Vec<_>>(), ["0"]);
+    }
+
+    #[test]
+    fn vanilla_requests_get_the_privacy_headers_too() {
+        let headers = build_headers(None, |builder| {
+            builder
+                .with_do_not_track(true)
+                .with_global_privacy_control(true);
+        });
+
+        assert_eq!(header_names(&headers), ["dnt", "sec-gpc"]);
+    }
+}
@@ -1,3 +1,31 @@
+/// The content encodings `impit` can decode, i.e. the enabled decoder features (`gzip`, `deflate`, `brotli` and `zstd`).
+///
+/// The other encodings are dropped from the profiles' `accept-encoding` headers, see `decodable_accept_encoding`.
+pub static DECODED_CONTENT_ENCODINGS: &[&str] = &[
+    #[cfg(feature = "gzip")]
+    "gzip",
+    #[cfg(feature = "deflate")]
+    "deflate",
+    #[cfg(feature = "brotli")]
+    "br",
+    #[cfg(feature = "zstd")]
+    "zstd",
+];
+
+/// The operating system all the built-in profiles impersonate.
+pub static PROFILE_PLATFORM: &str = "Linux";
+
+pub static CHROME_VERSION: u16 = 125;
+pub static FIREFOX_VERSION: u16 = 128;
+
+/// On HTTP/1.1, the browsers send the `Connection` header right before these headers.
+pub static CHROME_CONNECTION_HEADER_BEFORE: &str = "sec-ch-ua";
+pub static FIREFOX_CONNECTION_HEADER_BEFORE: &str = "sec-fetch-dest";
+
+/// The browsers with Do Not Track or Global Privacy Control enabled send the `DNT` and `Sec-GPC` headers right before these headers.
+pub static CHROME_PRIVACY_HEADERS_BEFORE: &str = "sec-fetch-site";
+pub static FIREFOX_PRIVACY_HEADERS_BEFORE: &str = "sec-fetch-dest";
+
 // [TODO!]
 // Note that not all requests are made the same:
 //  - on forced (Ctrl+R) reloads, Chrome sets Cache-Control: max-age=0
@@ -15,8 +43,56 @@ pub static CHROME_HEADERS: &[(&str, &str)] = &[
     ("sec-fetch-dest", "document"),
     ("accept-encoding", "gzip, deflate, br, zstd"),
     ("accept-language", "en-US,en;q=0.9"),
+    ("priority", "u=0, i"),
+];
+
+/// The `accept` header values Chrome sends for the `sec-fetch-dest` destinations other than `document`, see `RequestOptions::with_resource_type`.
+pub static CHROME_ACCEPT_HEADERS: &[(&str, &str)] = &[
+    (
+        "image",
+        "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8",
+    ),
+    ("script", "*/*"),
+    ("style", "text/css,*/*;q=0.1"),
+    ("font", "*/*"),
+    ("empty", "*/*"),
+];
+
+/// The RFC 9218 `priority` header values Chrome sends for the `sec-fetch-dest` destinations, see `RequestOptions::with_sec_fetch`.
+pub static CHROME_PRIORITIES: &[(&str, &str)] = &[
+    ("document", "u=0, i"),
+    ("iframe", "u=0, i"),
+    ("style", "u=0"),
+    ("font", "u=0"),
+    ("script", "u=1"),
+    ("empty", "u=1, i"),
+    ("image", "i"),
+];
+
+/// Plausible variants of the Chrome profile, see `ImpitBuilder::with_profile_jitter`.
+///
+/// Each variant overrides some of the `CHROME_HEADERS` values. All the variants share the TLS and HTTP/2 fingerprint of the base profile.
+pub static CHROME_HEADER_VARIANTS: &[&[(&str, &str)]] = &[
+    &[],
+    &[
+        ("sec-ch-ua", "\"Google Chrome\";v=\"124\", \"Chromium\";v=\"124\", \"Not-A.Brand\";v=\"99\""),
+        ("user-agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36"),
+    ],
+    &[
+        ("sec-ch-ua", "\"Google Chrome\";v=\"126\", \"Chromium\";v=\"126\", \"Not/A)Brand\";v=\"8\""),
+        ("user-agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36"),
+    ],
+    &[
+        ("accept-language", "en-US,en;q=0.9,de;q=0.8"),
+    ],
+    &[
+        ("sec-ch-ua", "\"Google Chrome\";v=\"126\", \"Chromium\";v=\"126\", \"Not/A)Brand\";v=\"8\""),
+        ("user-agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36"),
+        ("accept-language", "en-GB,en-US;q=0.9,en;q=0.8"),
+    ],
 ];
 
+#[cfg(feature = "websocket")]
 pub static CHROME_SOCKET_HEADERS: &[(&str, &str)] = &[
     ("user-agent", "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/125.0.0.0 Safari/537.36"),
     ("upgrade", "websocket"),
@@ -49,6 +125,55 @@ pub static FIREFOX_HEADERS: &[(&str, &str)] = &[
     ("Priority", "u=0, i"),
 ];
 
+/// The `Accept` header values Firefox sends for the `sec-fetch-dest` destinations other than `document`, see `RequestOptions::with_resource_type`.
+pub static FIREFOX_ACCEPT_HEADERS: &[(&str, &str)] = &[
+    (
+        "image",
+        "image/avif,image/webp,image/png,image/svg+xml,image/*;q=0.8,*/*;q=0.5",
+    ),
+    ("script", "*/*"),
+    ("style", "text/css,*/*;q=0.1"),
+    (
+        "font",
+        "application/font-woff2;q=1.0,application/font-woff;q=0.9,*/*;q=0.8",
+    ),
+    ("empty", "*/*"),
+];
+
+/// The RFC 9218 `Priority` header values Firefox sends for the `sec-fetch-dest` destinations, see `RequestOptions::with_sec_fetch`.
+pub static FIREFOX_PRIORITIES: &[(&str, &str)] = &[
+    ("document", "u=0, i"),
+    ("iframe", "u=4, i"),
+    ("style", "u=2"),
+    ("script", "u=2"),
+    ("font", "u=3"),
+    ("empty", "u=4"),
+    ("image", "u=5, i"),
+];
+
+/// Plausible variants of the Firefox profile, see `ImpitBuilder::with_profile_jitter`.
+///
+/// Each variant overrides some of the `FIREFOX_HEADERS` values. All the variants share the TLS and HTTP/2 fingerprint of the base profile.
+pub static FIREFOX_HEADER_VARIANTS: &[&[(&str, &str)]] = &[
+    &[],
+    &[(
+        "User-Agent",
+        "Mozilla/5.0 (X11; Linux x86_64; rv:127.0) Gecko/20100101 Firefox/127.0",
+    )],
+    &[(
+        "User-Agent",
+        "Mozilla/5.0 (X11; Linux x86_64; rv:129.0) Gecko/20100101 Firefox/129.0",
+    )],
+    &[("Accept-Language", "en-US,en;q=0.5")],
+    &[
+        (
+            "User-Agent",
+            "Mozilla/5.0 (X11; Linux x86_64; rv:129.0) Gecko/20100101 Firefox/129.0",
+        ),
+        ("Accept-Language", "en-US,en;q=0.5"),
+    ],
+];
+
 pub static FIREFOX_PSEUDOHEADERS_ORDER: [&str; 6] = [
     ":method",
     ":path",
@@ -56,12 +56,44 @@
 //!
 //! Note that you also have to build your project with `rustflags = "--cfg reqwest_unstable"`, otherwise, the build will also fail.
 //! This is because `impit` uses unstable features of `reqwest` (namely `http3` support), which are not available in the stable version of the library.
+//!
+//! ### Cargo features
+//!
+//! - `http3` (default) - HTTP/3 support ([`ImpitBuilder::with_http3`](crate::impit::ImpitBuilder::with_http3) and related methods). Pulls in the `hickory` DNS crates.
+//! - `websocket` (default) - WebSocket support ([`Impit::open_socket`](crate::impit::Impit::open_socket) and the [`websocket`](crate::websocket) module).
+//!   `tokio-tungstenite` is a dependency either way, so the [`ErrorType`](crate::impit::ErrorType) variants are the same with all the feature combinations.
+//! - `unix-socket` - sending the requests over Unix domain sockets ([`ImpitBuilder::with_unix_socket`](crate::impit::ImpitBuilder::with_unix_socket)), only on Unix platforms. Pulls in `hyper` and `hyper-util` as direct dependencies.
+//! - `ntlm` - the NTLM proxy authentication for [`Impit::connect_tunnel`](crate::impit::Impit::connect_tunnel) through HTTP proxies.
+//!   The handshake (NTLMv2) is implemented in Rust, so it works on all platforms without SSPI or GSSAPI - but the credentials have to be
+//!   passed in the proxy URL, the logged-in Windows user isn't used. Pulls in `hyper` and `hyper-util` as direct dependencies.
+//! - `debug` - debugging helpers, like [`Impit::client_hello`](crate::impit::Impit::client_hello).
+//! - `gzip`, `deflate`, `brotli`, `zstd` (default) - the response decoders. The built-in browser profiles only advertise the enabled encodings
+//!   in `Accept-Encoding` (just `identity` without any of them).
+//!
+//! Use `default-features = false, features = ["gzip", "deflate", "brotli", "zstd"]` to build a lighter HTTP/1.1 and HTTP/2 only client.
 
 #![deny(unused_crate_dependencies)]
+
+// The Unix socket transport is only built on Unix platforms.
+#[cfg(all(not(unix), feature = "unix-socket", not(feature = "ntlm")))]
+use {hyper as _, hyper_util as _};
+
+mod auth;
+mod circuit_breaker;
+mod connection_counter;
+mod form;
+mod host_filter;
+mod hsts;
 mod http_headers;
+#[cfg(feature = "ntlm")]
+mod ntlm;
+mod pacing;
+mod proxy_pool;
+mod random;
 mod response_parsing;
 mod tls;
 
+#[cfg(feature = "http3")]
 pub(crate) mod http3;
 
 /// Main module that contains the `Impit` struct and its methods.
@@ -70,6 +102,28 @@ pub mod impit;
 /// Customizing request options.
 pub mod request;
 
+/// Helpers for working with the responses returned by [`Impit`](crate::impit::Impit).
+pub mod response;
+
+/// gRPC-web message framing, see [`Impit::grpc_web_call`](crate::impit::Impit::grpc_web_call).
+pub mod grpc_web;
+
+/// Replaying the requests recorded in HAR (HTTP Archive) files.
+pub mod har;
+
+/// Persisting the cookies between runs, see [`ImpitBuilder::with_cookie_storage`](crate::impit::ImpitBuilder::with_cookie_storage).
+pub mod cookies;
+
+/// Custom transports for sending the requests, e.g. for testing without network access.
+pub mod transport;
+
+/// Server-Sent Events (SSE) support.
+pub mod sse;
+
+/// WebSocket helpers built on top of [`Impit::open_socket`](crate::impit::Impit::open_socket).
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
 /// Contains browser emulation-related types and functions.
 pub mod emulation {
 
@@ -83,11 +137,40 @@ pub mod emulation {
         Chrome,
         Firefox,
     }
+
+    impl Browser {
+        /// Returns all the browsers `impit` can impersonate.
+        pub fn all() -> &'static [Browser] {
+            &[Browser::Chrome, Browser::Firefox]
+        }
+    }
+
+    /// The details of a built-in browser profile, as returned by [`profile_info`].
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ProfileInfo {
+        /// The impersonated `User-Agent` header.
+        pub user_agent: &'static str,
+        /// The major version of the impersonated browser.
+        pub version: u16,
+        /// The impersonated operating system, e.g. `Linux`.
+        pub platform: &'static str,
+    }
+
+    /// Returns the details of the built-in profile for `browser`, e.g. for listing the available impersonations in a UI.
+    pub fn profile_info(browser: Browser) -> ProfileInfo {
+        crate::http_headers::profile_info(browser)
+    }
 }
 
 /// Various utility functions and types.
 pub mod utils {
+    pub use crate::response_parsing::content_disposition_filename;
     pub use crate::response_parsing::decode;
+    pub use crate::response_parsing::decode_strict;
+    pub use crate::response_parsing::html_metadata;
+    pub use crate::response_parsing::AltService;
+    pub use crate::response_parsing::AltSvc;
     pub use crate::response_parsing::ContentType;
+    pub use crate::response_parsing::HtmlMetadata;
     pub use encoding::all as encodings;
 }
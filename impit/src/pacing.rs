@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::random::Rng;
+
+/// Keeps a random delay between consecutive requests, see [`ImpitBuilder::with_request_delay`](crate::impit::ImpitBuilder::with_request_delay).
+pub struct RequestPacer {
+    min: Duration,
+    max: Duration,
+    per_host: bool,
+    /// The instants of the last requests, per host (or under the empty key, if the delay is global).
+    last_requests: HashMap<String, Instant>,
+    rng: Rng,
+}
+
+impl RequestPacer {
+    pub fn new(min: Duration, max: Duration, per_host: bool) -> Self {
+        RequestPacer {
+            min,
+            max: max.max(min),
+            per_host,
+            last_requests: HashMap::new(),
+            rng: Rng::default(),
+        }
+    }
+
+    fn random_delay(&mut self) -> Duration {
+        let range = (self.max - self.min).as_nanos() as u64;
+        if range == 0 {
+            return self.min;
+        }
+
+        self.min + Duration::from_nanos(self.rng.below(range + 1))
+    }
+
+    /// Waits until the random delay since the previous request (to `host`, if the delay is per-host) has passed.
+    pub async fn wait(&mut self, host: &str) {
+        let key = if self.per_host { host } else { "" };
+        let delay = self.random_delay();
+
+        if let Some(last_request) = self.last_requests.get(key) {
+            let ready_at = *last_request + delay;
+            let now = Instant::now();
+
+            if ready_at > now {
+                tokio::time::sleep(ready_at - now).await;
+            }
+        }
+
+        self.last_requests.insert(key.to_owned(), Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns how long the `wait` call took (on the paused test clock).
+    async fn timed_wait(pacer: &mut RequestPacer, host: &str) -> Duration {
+        let start = Instant::now();
+        pacer.wait(host).await;
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delays_stay_within_the_range() {
+        let mut pacer = RequestPacer::new(Duration::from_secs(1), Duration::from_secs(2), false);
+
+        assert_eq!(timed_wait(&mut pacer, "example.com").await, Duration::ZERO);
+        for _ in 0..20 {
+            // The timers have a millisecond resolution.
+            let delay = timed_wait(&mut pacer, "example.com").await;
+            assert!(delay >= Duration::from_secs(1));
+            assert!(delay <= Duration::from_secs(2) + Duration::from_millis(1));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn max_below_min_keeps_the_min_delay() {
+        let mut pacer = RequestPacer::new(Duration::from_secs(3), Duration::from_secs(1), false);
+
+        pacer.wait("example.com").await;
+        assert_eq!(
+            timed_wait(&mut pacer, "example.com").await,
+            Duration::from_secs(3)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn the_delay_counts_from_the_previous_request() {
+        let mut pacer = RequestPacer::new(Duration::from_secs(2), Duration::from_secs(2), false);
+
+        pacer.wait("example.com").await;
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(
+            timed_wait(&mut pacer, "example.com").await,
+            Duration::from_millis(1500)
+        );
+
+        // Requests coming late enough aren't held back.
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert_eq!(timed_wait(&mut pacer, "example.com").await, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn per_host_delays_space_every_host_separately() {
+        let mut pacer = RequestPacer::new(Duration::from_secs(1), Duration::from_secs(1), true);
+
+        assert_eq!(timed_wait(&mut pacer, "a.example").await, Duration::ZERO);
+        assert_eq!(timed_wait(&mut pacer, "b.example").await, Duration::ZERO);
+        assert_eq!(
+            timed_wait(&mut pacer, "a.example").await,
+            Duration::from_secs(1)
+        );
+
+        // `b.example` was last requested a second ago.
+        assert_eq!(timed_wait(&mut pacer, "b.example").await, Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn global_delays_space_all_the_hosts() {
+        let mut pacer = RequestPacer::new(Duration::from_secs(1), Duration::from_secs(1), false);
+
+        assert_eq!(timed_wait(&mut pacer, "a.example").await, Duration::ZERO);
+        assert_eq!(
+            timed_wait(&mut pacer, "b.example").await,
+            Duration::from_secs(1)
+        );
+    }
+}
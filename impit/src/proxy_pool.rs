@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::impit::RotationStrategy;
+use crate::random::Rng;
+
+/// Picks the proxy for every request from a pool of proxies, according to the [`RotationStrategy`].
+pub struct ProxyPool {
+    proxies: Vec<String>,
+    strategy: RotationStrategy,
+    /// How long a failed proxy is skipped for. `None` disables the health tracking.
+    cooldown: Option<Duration>,
+    /// The index of the next proxy for the round-robin rotation (and the sticky assignments).
+    cursor: usize,
+    /// The proxy index assigned to every host with [`RotationStrategy::StickyPerHost`].
+    hosts: HashMap<String, usize>,
+    /// The proxies that failed recently, with the instant they can be used again.
+    unhealthy: HashMap<usize, Instant>,
+    /// The random number generator for [`RotationStrategy::Random`].
+    rng: Rng,
+}
+
+impl ProxyPool {
+    pub fn new(
+        proxies: Vec<String>,
+        strategy: RotationStrategy,
+        cooldown: Option<Duration>,
+    ) -> Self {
+        ProxyPool {
+            proxies,
+            strategy,
+            cooldown,
+            cursor: 0,
+            hosts: HashMap::new(),
+            unhealthy: HashMap::new(),
+            rng: Rng::default(),
+        }
+    }
+
+    pub fn proxy_count(&self) -> usize {
+        self.proxies.len()
+    }
+
+    /// Returns `true` if the failed proxies should be skipped (and the requests failed over to the other proxies).
+    pub fn tracks_health(&self) -> bool {
+        self.cooldown.is_some()
+    }
+
+    /// Returns the proxy URL to use for a request to `host`, skipping the unhealthy proxies.
+    ///
+    /// Returns `None` if all the proxies are unhealthy.
+    pub fn next_proxy(&mut self, host: &str) -> Option<&str> {
+        let index = match self.strategy {
+            RotationStrategy::RoundRobin => self.next_healthy_index()?,
+            RotationStrategy::Random => {
+                let start = self.rng.below(self.proxies.len() as u64) as usize;
+                self.healthy_index_from(start)?
+            }
+            RotationStrategy::StickyPerHost => match self.hosts.get(host) {
+                Some(index) if self.is_healthy(*index) => *index,
+                _ => {
+                    let index = self.next_healthy_index()?;
+                    self.hosts.insert(host.to_owned(), index);
+                    index
+                }
+            },
+        };
+
+        Some(&self.proxies[index])
+    }
+
+    /// Returns the proxy [`ProxyPool::next_proxy`] would pick for `host`, without advancing the rotation.
+    ///
+    /// The random rotation can't be predicted, so the first healthy proxy from the current position is returned for it.
+    pub fn peek_proxy(&self, host: &str) -> Option<&str> {
+        let index = match self.hosts.get(host) {
+            Some(index)
+                if self.strategy == RotationStrategy::StickyPerHost && self.is_healthy(*index) =>
+            {
+                *index
+            }
+            _ => self.healthy_index_from(self.cursor)?,
+        };
+
+        Some(&self.proxies[index])
+    }
+
+    /// Skips the proxy for the cooldown period. Has no effect if the health tracking is disabled.
+    pub fn mark_unhealthy(&mut self, proxy: &str) {
+        let (Some(cooldown), Some(index)) = (self.cooldown, self.index_of(proxy)) else {
+            return;
+        };
+
+        self.unhealthy.insert(index, Instant::now() + cooldown);
+    }
+
+    pub fn mark_healthy(&mut self, proxy: &str) {
+        if let Some(index) = self.index_of(proxy) {
+            self.unhealthy.remove(&index);
+        }
+    }
+
+    fn index_of(&self, proxy: &str) -> Option<usize> {
+        self.proxies.iter().position(|p| p == proxy)
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        !matches!(self.unhealthy.get(&index), Some(until) if Instant::now() < *until)
+    }
+
+    /// Returns the first healthy proxy index, starting at `start` and wrapping around.
+    fn healthy_index_from(&self, start: usize) -> Option<usize> {
+        (0..self.proxies.len())
+            .map(|offset| (start + offset) % self.proxies.len())
+            .find(|index| self.is_healthy(*index))
+    }
+
+    fn next_healthy_index(&mut self) -> Option<usize> {
+        let index = self.healthy_index_from(self.cursor)?;
+        self.cursor = (index + 1) % self.proxies.len();
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(strategy: RotationStrategy, cooldown: Option<Duration>) -> ProxyPool {
+        ProxyPool::new(
+            vec!["http://a:1".to_owned(), "http://b:1".to_owned()],
+            strategy,
+            cooldown,
+        )
+    }
+
+    fn round_robin_pool(cooldown: Option<Duration>) -> ProxyPool {
+        ProxyPool::new(
+            vec![
+                "http://a:1".to_owned(),
+                "http://b:1".to_owned(),
+                "http://c:1".to_owned(),
+            ],
+            RotationStrategy::RoundRobin,
+            cooldown,
+        )
+    }
+
+    fn rotation(pool: &mut ProxyPool, hosts: &[&str]) -> Vec<Option<String>> {
+        hosts
+            .iter()
+            .map(|host| pool.next_proxy(host).map(str::to_owned))
+            .collect()
+    }
+
+    fn proxies(names: &[&str]) -> Vec<Option<String>> {
+        names
+            .iter()
+            .map(|name| Some(format!("http://{name}:1")))
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_rotates_in_order_regardless_of_the_host() {
+        let mut pool = round_robin_pool(None);
+
+        assert_eq!(
+            rotation(
+                &mut pool,
+                &["x.com", "x.com", "y.com", "x.com", "z.com", "y.com", "x.com"]
+            ),
+            proxies(&["a", "b", "c", "a", "b", "c", "a"])
+        );
+    }
+
+    #[test]
+    fn round_robin_skips_the_unhealthy_proxies() {
+        let mut pool = round_robin_pool(Some(Duration::from_secs(60)));
+
+        assert_eq!(rotation(&mut pool, &["x.com"]), proxies(&["a"]));
+        pool.mark_unhealthy("http://b:1");
+        assert_eq!(
+            rotation(&mut pool, &["x.com"; 4]),
+            proxies(&["c", "a", "c", "a"])
+        );
+
+        // A recovered proxy gets its turn again.
+        pool.mark_healthy("http://b:1");
+        assert_eq!(
+            rotation(&mut pool, &["x.com"; 3]),
+            proxies(&["b", "c", "a"])
+        );
+
+        for proxy in ["http://a:1", "http://b:1", "http://c:1"] {
+            pool.mark_unhealthy(proxy);
+        }
+        assert_eq!(pool.next_proxy("x.com"), None);
+    }
+
+    #[test]
+    fn round_robin_uses_the_proxies_again_after_the_cooldown() {
+        let mut pool = round_robin_pool(Some(Duration::ZERO));
+
+        pool.mark_unhealthy("http://a:1");
+        assert_eq!(
+            rotation(&mut pool, &["x.com"; 4]),
+            proxies(&["a", "b", "c", "a"])
+        );
+    }
+
+    #[test]
+    fn round_robin_ignores_failures_without_health_tracking() {
+        let mut pool = round_robin_pool(None);
+
+        pool.mark_unhealthy("http://a:1");
+        pool.mark_unhealthy("http://b:1");
+        assert!(!pool.tracks_health());
+        assert_eq!(
+            rotation(&mut pool, &["x.com"; 3]),
+            proxies(&["a", "b", "c"])
+        );
+    }
+
+    #[test]
+    fn peeking_doesnt_advance_the_rotation() {
+        let mut pool = pool(RotationStrategy::RoundRobin, Some(Duration::from_secs(60)));
+
+        assert_eq!(pool.peek_proxy("example.com"), Some("http://a:1"));
+        assert_eq!(pool.peek_proxy("example.com"), Some("http://a:1"));
+        assert_eq!(pool.next_proxy("example.com"), Some("http://a:1"));
+
+        assert_eq!(pool.peek_proxy("example.com"), Some("http://b:1"));
+        pool.mark_unhealthy("http://b:1");
+        assert_eq!(pool.peek_proxy("example.com"), Some("http://a:1"));
+        pool.mark_unhealthy("http://a:1");
+        assert_eq!(pool.peek_proxy("example.com"), None);
+    }
+
+    #[test]
+    fn peeking_returns_the_sticky_proxy() {
+        let mut pool = pool(RotationStrategy::StickyPerHost, None);
+
+        assert_eq!(pool.next_proxy("a.example"), Some("http://a:1"));
+        assert_eq!(pool.peek_proxy("a.example"), Some("http://a:1"));
+        assert_eq!(pool.peek_proxy("b.example"), Some("http://b:1"));
+        assert_eq!(pool.next_proxy("b.example"), Some("http://b:1"));
+    }
+}
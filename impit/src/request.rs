@@ -1,10 +1,118 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+
+use reqwest::header::HeaderValue;
+
+use crate::emulation::Browser;
+
+/// The values of the `Sec-Fetch-*` fetch metadata headers.
+///
+/// See [`RequestOptions::with_sec_fetch`] for more details.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecFetch {
+    /// The `Sec-Fetch-Site` header value, e.g. `none`, `same-origin`, `same-site` or `cross-site`.
+    pub site: String,
+    /// The `Sec-Fetch-Mode` header value, e.g. `navigate`, `cors`, `no-cors` or `websocket`.
+    pub mode: String,
+    /// The `Sec-Fetch-Dest` header value, e.g. `document`, `empty`, `image` or `script`.
+    pub dest: String,
+    /// Whether the request was triggered by a user activation. If `false`, the `Sec-Fetch-User` header is omitted (as browsers do).
+    pub user: bool,
+}
+
+/// The compression algorithms for request bodies, see [`RequestOptions::with_body_compression`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentEncoding {
+    /// `Content-Encoding: gzip`
+    Gzip,
+    /// `Content-Encoding: deflate` (the zlib format, as defined by the HTTP specification).
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// Returns the `Content-Encoding` header value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// The kind of resource a request fetches, see [`RequestOptions::with_resource_type`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceType {
+    /// A top-level navigation (the default of the browser profiles).
+    Document,
+    /// An `<img>` image.
+    Image,
+    /// A `<script>` script.
+    Script,
+    /// A `<link rel="stylesheet">` stylesheet.
+    Style,
+    /// A web font from a stylesheet.
+    Font,
+    /// A `fetch()` or `XMLHttpRequest` request.
+    Fetch,
+}
+
+impl ResourceType {
+    /// Returns the `Sec-Fetch-Dest` header value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceType::Document => "document",
+            ResourceType::Image => "image",
+            ResourceType::Script => "script",
+            ResourceType::Style => "style",
+            ResourceType::Font => "font",
+            ResourceType::Fetch => "empty",
+        }
+    }
+
+    /// Returns the `Sec-Fetch-*` values the browsers send for the resource type.
+    ///
+    /// The subresources are assumed to be same-origin, override it with [`RequestOptions::with_sec_fetch`] if needed.
+    pub(crate) fn sec_fetch(&self) -> SecFetch {
+        let (site, mode) = match self {
+            ResourceType::Document => ("none", "navigate"),
+            ResourceType::Image | ResourceType::Script | ResourceType::Style => {
+                ("same-origin", "no-cors")
+            }
+            ResourceType::Font | ResourceType::Fetch => ("same-origin", "cors"),
+        };
+
+        SecFetch {
+            site: site.to_string(),
+            mode: mode.to_string(),
+            dest: self.as_str().to_string(),
+            user: *self == ResourceType::Document,
+        }
+    }
+}
+
+/// A function customizing the underlying `reqwest` request, see [`RequestOptions::with_request_modifier`].
+#[derive(Clone)]
+pub struct RequestModifier(
+    Arc<dyn Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder + Send + Sync>,
+);
+
+impl RequestModifier {
+    pub(crate) fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        (self.0)(request)
+    }
+}
+
+impl fmt::Debug for RequestModifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RequestModifier")
+    }
+}
 
 /// A struct that holds the request options.
 ///
 /// Unlike the [`ImpitBuilder`](crate::impit::ImpitBuilder) struct, these options are specific to a single request.
 ///
 /// Used by the [`Impit`](crate::impit::Impit) struct's methods.
+
 #[derive(Debug, Clone, Default)]
 pub struct RequestOptions {
     /// A `HashMap` that holds custom HTTP headers. These are added to the default headers and should never overwrite them.
@@ -15,4 +123,522 @@ pub struct RequestOptions {
     ///
     /// If [`ImpitBuilder::with_http3`](crate::impit::ImpitBuilder::with_http3) wasn't called, this option will cause [`ErrorType::Http3Disabled`](crate::impit::ErrorType::Http3Disabled) errors.
     pub http3_prior_knowledge: bool,
+    /// Overrides the hostname sent in the TLS SNI extension. The connection still goes to the host from the request URL.
+    ///
+    /// See [`RequestOptions::with_sni`] for more details.
+    pub sni: Option<String>,
+    /// Omits the TLS SNI extension from the ClientHello.
+    ///
+    /// See [`RequestOptions::without_sni`] for more details.
+    pub disable_sni: bool,
+    /// Overrides the `Host` header (and `:authority` on HTTP/2 and HTTP/3). The connection still goes to the host from the request URL.
+    ///
+    /// See [`RequestOptions::with_host_header`] for more details.
+    pub host_header: Option<String>,
+    /// Sends the `Expect: 100-continue` header with the request body.
+    ///
+    /// See [`RequestOptions::with_expect_continue_header`] for more details.
+    pub expect_continue_header: bool,
+    /// Sends the request body with chunked transfer encoding instead of a `Content-Length` header.
+    ///
+    /// See [`RequestOptions::with_chunked_body`] for more details.
+    pub chunked: bool,
+    /// Impersonates a different browser's HTTP headers for this request.
+    ///
+    /// See [`RequestOptions::with_browser_override`] for more details.
+    pub browser_override: Option<Browser>,
+    /// Sends the request without the impersonated browser headers.
+    ///
+    /// See [`RequestOptions::without_impersonation`] for more details.
+    pub disable_impersonation: bool,
+    /// Sends the `Connection: close` header, so the connection isn't reused for other requests.
+    ///
+    /// See [`RequestOptions::with_connection_close`] for more details.
+    pub connection_close: bool,
+    /// Fails the request instead of sending a re-encoded query string.
+    ///
+    /// See [`RequestOptions::with_verbatim_query`] for more details.
+    pub verbatim_query: bool,
+    /// Sends the request without the `Accept-Encoding` header.
+    ///
+    /// See [`RequestOptions::without_accept_encoding`] for more details.
+    pub disable_accept_encoding: bool,
+    /// Skips the TLS certificate validation for this request.
+    ///
+    /// See [`RequestOptions::with_ignore_tls_errors`] for more details.
+    pub ignore_tls_errors: bool,
+    /// Customizes the underlying `reqwest` request before it's sent.
+    ///
+    /// See [`RequestOptions::with_request_modifier`] for more details.
+    pub request_modifier: Option<RequestModifier>,
+    /// Overrides the impersonated `Sec-Fetch-*` headers.
+    ///
+    /// See [`RequestOptions::with_sec_fetch`] for more details.
+    pub sec_fetch: Option<SecFetch>,
+    /// The kind of the fetched resource, setting the `Accept` and `Sec-Fetch-*` headers.
+    ///
+    /// See [`RequestOptions::with_resource_type`] for more details.
+    pub resource_type: Option<ResourceType>,
+    /// The RFC 9218 `priority` header value, e.g. `u=1, i`.
+    ///
+    /// See [`RequestOptions::with_priority`] for more details.
+    pub priority: Option<HeaderValue>,
+    /// Overrides the client-wide redirect limit for this request.
+    ///
+    /// See [`RequestOptions::with_max_redirects`] for more details.
+    pub max_redirects: Option<usize>,
+    /// The username and password for the HTTP Digest authentication.
+    ///
+    /// See [`RequestOptions::with_digest_auth`] for more details.
+    pub digest_auth: Option<(String, String)>,
+    /// Follows the `<meta http-equiv="refresh">` redirects in HTML responses.
+    ///
+    /// See [`RequestOptions::with_follow_meta_refresh`] for more details.
+    pub follow_meta_refresh: bool,
+    /// Compresses the request body.
+    ///
+    /// See [`RequestOptions::with_body_compression`] for more details.
+    pub body_compression: Option<ContentEncoding>,
+    /// Returns the 4xx and 5xx responses as errors.
+    ///
+    /// See [`RequestOptions::with_error_for_status`] for more details.
+    pub error_for_status: bool,
+    /// Cookies sent with this request only, on top of the cookies from the cookie store.
+    ///
+    /// See [`RequestOptions::with_extra_cookie`] for more details.
+    pub extra_cookies: Vec<(String, String)>,
+    /// The flags set with the builder methods taking a `bool` (e.g. [`RequestOptions::with_error_for_status`]),
+    /// so that setting them to `false` turns off the flags enabled in the client-wide defaults.
+    ///
+    /// Maintained by the builder methods, see [`ImpitBuilder::with_default_request_options`](crate::impit::ImpitBuilder::with_default_request_options).
+    pub explicit_flags: ExplicitFlags,
+}
+
+/// The flags of [`RequestOptions`] that were set explicitly, see [`RequestOptions::explicit_flags`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExplicitFlags {
+    follow_meta_refresh: bool,
+    error_for_status: bool,
+}
+
+impl RequestOptions {
+    /// Merges the options with the client-wide `defaults`, see [`ImpitBuilder::with_default_request_options`](crate::impit::ImpitBuilder::with_default_request_options).
+    pub(crate) fn merged_with(mut self, defaults: &RequestOptions) -> RequestOptions {
+        for (name, value) in &defaults.headers {
+            if !self
+                .headers
+                .keys()
+                .any(|existing| existing.eq_ignore_ascii_case(name))
+            {
+                self.headers.insert(name.clone(), value.clone());
+            }
+        }
+
+        // The later cookies replace the earlier ones with the same name.
+        let mut extra_cookies = defaults.extra_cookies.clone();
+        extra_cookies.append(&mut self.extra_cookies);
+
+        // The flags set with the builder methods take precedence, the other ones are enabled if enabled in either of the options.
+        let follow_meta_refresh = match self.explicit_flags.follow_meta_refresh {
+            true => self.follow_meta_refresh,
+            false => self.follow_meta_refresh || defaults.follow_meta_refresh,
+        };
+        let error_for_status = match self.explicit_flags.error_for_status {
+            true => self.error_for_status,
+            false => self.error_for_status || defaults.error_for_status,
+        };
+
+        RequestOptions {
+            headers: self.headers,
+            timeout: self.timeout.or(defaults.timeout),
+            http3_prior_knowledge: self.http3_prior_knowledge || defaults.http3_prior_knowledge,
+            sni: self.sni.or_else(|| defaults.sni.clone()),
+            disable_sni: self.disable_sni || defaults.disable_sni,
+            host_header: self.host_header.or_else(|| defaults.host_header.clone()),
+            expect_continue_header: self.expect_continue_header || defaults.expect_continue_header,
+            chunked: self.chunked || defaults.chunked,
+            browser_override: self.browser_override.or(defaults.browser_override),
+            disable_impersonation: self.disable_impersonation || defaults.disable_impersonation,
+            connection_close: self.connection_close || defaults.connection_close,
+            verbatim_query: self.verbatim_query || defaults.verbatim_query,
+            disable_accept_encoding: self.disable_accept_encoding
+                || defaults.disable_accept_encoding,
+            ignore_tls_errors: self.ignore_tls_errors || defaults.ignore_tls_errors,
+            request_modifier: self
+                .request_modifier
+                .or_else(|| defaults.request_modifier.clone()),
+            sec_fetch: self.sec_fetch.or_else(|| defaults.sec_fetch.clone()),
+            resource_type: self.resource_type.or(defaults.resource_type),
+            priority: self.priority.or_else(|| defaults.priority.clone()),
+            max_redirects: self.max_redirects.or(defaults.max_redirects),
+            digest_auth: self.digest_auth.or_else(|| defaults.digest_auth.clone()),
+            follow_meta_refresh,
+            body_compression: self.body_compression.or(defaults.body_compression),
+            error_for_status,
+            explicit_flags: self.explicit_flags,
+            extra_cookies,
+        }
+    }
+
+    /// Sends `hostname` in the TLS SNI extension instead of the request URL host.
+    ///
+    /// The TCP connection is still made to the host from the request URL, and the `Host` header is kept intact.
+    /// This is useful e.g. when connecting to a server by its IP address.
+    ///
+    /// Note that the server certificate is validated against `hostname`, not the URL host.
+    /// On HTTP/2 and HTTP/3, the `:authority` pseudo-header follows the SNI hostname.
+    ///
+    /// **Security:** a mismatch between SNI and `Host` is what domain fronting relies on. Some CDNs treat it as abuse and block such requests.
+    pub fn with_sni(mut self, hostname: impl Into<String>) -> Self {
+        self.sni = Some(hostname.into());
+        self
+    }
+
+    /// Sends `host` in the `Host` header instead of the request URL host.
+    ///
+    /// The TCP connection is still made to the host from the request URL, which is useful e.g. for testing virtual hosts behind a load balancer.
+    /// `host` can contain a port (e.g. `example.com:8080`) and has to be a domain name, not an IP address.
+    ///
+    /// Unless [`RequestOptions::with_sni`] is used as well, the SNI extension (and certificate validation) follows `host`.
+    /// If both are set, `:authority` on HTTP/2 and HTTP/3 follows the SNI hostname, and only the HTTP/1.1 `Host` header is overridden.
+    ///
+    /// Internationalized domain names (both here and in [`RequestOptions::with_sni`]) are sent in their ASCII (punycode) form, e.g. `xn--bcher-kva.example`.
+    pub fn with_host_header(mut self, host: impl Into<String>) -> Self {
+        self.host_header = Some(host.into());
+        self
+    }
+
+    /// Sends the `Expect: 100-continue` header with requests that carry a body (e.g. large `PUT` uploads).
+    ///
+    /// Only the header is sent - the client doesn't wait for the `100 Continue` interim response, because the underlying HTTP client
+    /// (`hyper`) writes the body right after the headers. Servers that check the header can still reject the request early.
+    /// If the server doesn't accept the expectation (`417 Expectation Failed`), the request fails with the [`ErrorType::ExpectationFailed`](crate::impit::ErrorType::ExpectationFailed) error.
+    pub fn with_expect_continue_header(mut self) -> Self {
+        self.expect_continue_header = true;
+        self
+    }
+
+    /// Sends the request body as a stream of unknown length, without the `Content-Length` header.
+    ///
+    /// On HTTP/1.1, this results in the `Transfer-Encoding: chunked` encoding. On HTTP/2 and HTTP/3 (which don't support chunked encoding),
+    /// the body is sent as a sequence of `DATA` frames without the `content-length` header.
+    pub fn with_chunked_body(mut self) -> Self {
+        self.chunked = true;
+        self
+    }
+
+    /// Uses the HTTP headers (and their order) of `browser` for this request, instead of the client's browser.
+    ///
+    /// Note that only the HTTP headers change - the TLS fingerprint is bound to the connection
+    /// and stays the one of the browser set with [`ImpitBuilder::with_browser`](crate::impit::ImpitBuilder::with_browser).
+    /// The HTTP/2 pseudo-header order can't change per request either, it's shared by the whole process and set
+    /// by the first impersonating client. Mixing the two can make the request easier to detect.
+    pub fn with_browser_override(mut self, browser: Browser) -> Self {
+        self.browser_override = Some(browser);
+        self
+    }
+
+    /// Overrides the `Sec-Fetch-Site`, `Sec-Fetch-Mode`, `Sec-Fetch-Dest` and `Sec-Fetch-User` headers.
+    ///
+    /// The browser profiles impersonate a top-level navigation by default (`none`, `navigate`, `document`, `?1`).
+    /// For e.g. an XHR/fetch request, use `with_sec_fetch("same-origin", "cors", "empty", false)`.
+    ///
+    /// The values replace the profile defaults in their original positions. If `user` is `false`, the `Sec-Fetch-User` header is omitted.
+    /// Headers set explicitly in [`RequestOptions::headers`] still take precedence.
+    pub fn with_sec_fetch(mut self, site: &str, mode: &str, dest: &str, user: bool) -> Self {
+        self.sec_fetch = Some(SecFetch {
+            site: site.to_owned(),
+            mode: mode.to_owned(),
+            dest: dest.to_owned(),
+            user,
+        });
+        self
+    }
+
+    /// Impersonates the browser fetching a resource of the given type, e.g. an image, instead of a top-level navigation.
+    ///
+    /// This sets the `Accept` header and the `Sec-Fetch-*` headers to the values the impersonated browser uses for the resource type
+    /// (e.g. `Accept: image/avif,image/webp,...` with `Sec-Fetch-Dest: image`), and omits the navigation-only `Upgrade-Insecure-Requests` header.
+    /// The subresources are fetched as same-origin requests - use [`RequestOptions::with_sec_fetch`] (which takes precedence) for the cross-site ones.
+    /// The headers set explicitly in [`RequestOptions::headers`] still take precedence over both.
+    pub fn with_resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = Some(resource_type);
+        self
+    }
+
+    /// Sets the RFC 9218 `priority` header, which the browsers send to signal the importance of the resource.
+    ///
+    /// `urgency` ranges from `0` (the highest) to `7` (the lowest) and `incremental` marks resources that can be used while
+    /// they're downloading (e.g. HTML or progressive images), resulting in e.g. `u=1, i`.
+    ///
+    /// Without this, the browser profile default is sent - `u=0, i` for navigations, or the value the browser uses for the
+    /// destination set with [`RequestOptions::with_sec_fetch`] (e.g. `u=1` for Chrome's `script`). Note that the HTTP/2 stream
+    /// priorities (the frames) are set by the HTTP/2 fingerprint of the profile and don't follow this header.
+    pub fn with_priority(mut self, urgency: u8, incremental: bool) -> Self {
+        let urgency = urgency.min(7);
+
+        self.priority = Some(
+            match incremental {
+                true => HeaderValue::from_str(&format!("u={urgency}, i")),
+                false => HeaderValue::from_str(&format!("u={urgency}")),
+            }
+            .expect("The priority value should be a valid header value."),
+        );
+        self
+    }
+
+    /// Sends this request without the impersonated browser headers (`User-Agent`, `Accept`, `Sec-Fetch-*`, ...),
+    /// like the vanilla client would. Only the headers from [`RequestOptions::headers`] are sent (plus the ones `reqwest` adds).
+    ///
+    /// This is useful for API endpoints that reject browser-like requests. Note that the TLS fingerprint
+    /// is bound to the connection and stays the one of the browser set with [`ImpitBuilder::with_browser`](crate::impit::ImpitBuilder::with_browser).
+    pub fn without_impersonation(mut self) -> Self {
+        self.disable_impersonation = true;
+        self
+    }
+
+    /// Sends the `Connection: close` header instead of the browser's `Connection: keep-alive`, so the server closes the connection
+    /// after the response and the next request to the host opens a fresh one.
+    ///
+    /// This only has an effect on HTTP/1.1 - HTTP/2 and HTTP/3 don't allow the `Connection` header, and `hyper` strips it before sending the request.
+    pub fn with_connection_close(mut self) -> Self {
+        self.connection_close = true;
+        self
+    }
+
+    /// Guarantees that the query string is sent exactly as it appears in the request URL, e.g. for signed URLs.
+    ///
+    /// The query parameters are never reordered, and the existing percent-escapes are kept as they are. However, the URL parser
+    /// (following the WHATWG URL standard) percent-encodes the characters that aren't allowed in query strings - e.g. spaces,
+    /// quotes (including `'`), `<`, `>` and non-ASCII characters. Such normalization is usually harmless, but it invalidates signatures
+    /// computed over the raw query. With this option, the request fails with [`ErrorType::QueryNotPreserved`](crate::impit::ErrorType::QueryNotPreserved)
+    /// instead of sending a query string that differs from the original one.
+    pub fn with_verbatim_query(mut self) -> Self {
+        self.verbatim_query = true;
+        self
+    }
+
+    /// Omits the `Accept-Encoding` header, so the server responds with an uncompressed (identity) body.
+    ///
+    /// Unlike setting the header to a custom value, this removes it entirely - both the impersonated one and the one `reqwest`
+    /// adds for the automatic decompression. The request is sent with a separate client without the decompression,
+    /// so in the unlikely case the server compresses the body anyway, it's returned as it is.
+    ///
+    /// Note that browsers always send the header, so its absence makes the request easier to fingerprint.
+    pub fn without_accept_encoding(mut self) -> Self {
+        self.disable_accept_encoding = true;
+        self
+    }
+
+    /// Skips the TLS certificate validation for this request, e.g. for a known host with a self-signed certificate.
+    ///
+    /// The requests are sent with a separate client (built on the first such request), so they don't share the connections
+    /// with the validated requests. Combined with the proxy pool, a separate client is built for every proxy.
+    /// Has no effect if the TLS errors are ignored client-wide, see [`ImpitBuilder::with_ignore_tls_errors`](crate::impit::ImpitBuilder::with_ignore_tls_errors).
+    ///
+    /// **Security:** the connection is open to man-in-the-middle attacks, only use this for hosts you trust.
+    pub fn with_ignore_tls_errors(mut self) -> Self {
+        self.ignore_tls_errors = true;
+        self
+    }
+
+    /// Passes the prepared `reqwest` request (with the impersonated headers, the body, etc.) through `modifier` right before it's sent.
+    ///
+    /// This is an escape hatch for the `reqwest` features `impit` doesn't wrap, e.g. setting a request-specific header
+    /// after the impersonated ones, or basic authentication. The modifier also applies to [`Impit::build_request`](crate::impit::Impit::build_request)
+    /// and [`Impit::dump_request`](crate::impit::Impit::dump_request). Note that the changes can break the impersonation.
+    ///
+    /// ### Example
+    /// ```rust
+    /// let options = RequestOptions::default()
+    ///     .with_request_modifier(|request| request.basic_auth("user", Some("password")));
+    /// ```
+    pub fn with_request_modifier(
+        mut self,
+        modifier: impl Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder + Send + Sync + 'static,
+    ) -> Self {
+        self.request_modifier = Some(RequestModifier(Arc::new(modifier)));
+        self
+    }
+
+    /// Omits the TLS SNI extension entirely.
+    ///
+    /// Servers hosting multiple domains on one IP address might respond with a default certificate (or fail the handshake).
+    /// Note that an absent SNI extension is unusual for browsers and makes the request easier to fingerprint.
+    pub fn without_sni(mut self) -> Self {
+        self.disable_sni = true;
+        self
+    }
+
+    /// Follows at most `max_redirects` redirects for this request, regardless of the client-wide [`RedirectBehavior`](crate::impit::RedirectBehavior).
+    ///
+    /// Exceeding the limit results in the [`ErrorType::TooManyRedirects`](crate::impit::ErrorType::TooManyRedirects) error. `0` disables following redirects, i.e. the `3xx` response is returned as it is.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = Some(max_redirects);
+        self
+    }
+
+    /// Follows the `<meta http-equiv="refresh" content="0; url=...">` redirects in HTML responses.
+    ///
+    /// If the response is a `200 OK` HTML page with a meta refresh tag, a `GET` request to the refresh URL (resolved against the page URL) is made,
+    /// regardless of the refresh delay. The meta refreshes count towards the redirect limit (see [`RequestOptions::with_max_redirects`]),
+    /// after which the last page is returned. No meta refreshes are followed with [`RedirectBehavior::ManualRedirect`](crate::impit::RedirectBehavior::ManualRedirect).
+    ///
+    /// Note that this reads the whole body of HTML responses before returning them.
+    pub fn with_follow_meta_refresh(mut self, follow_meta_refresh: bool) -> Self {
+        self.follow_meta_refresh = follow_meta_refresh;
+        self.explicit_flags.follow_meta_refresh = true;
+        self
+    }
+
+    /// Compresses the request body with the given algorithm and sets the `Content-Encoding` header accordingly.
+    ///
+    /// This can considerably reduce the size of large payloads, but the server has to support compressed request bodies
+    /// (many don't, and respond with `415 Unsupported Media Type`). Browsers never compress request bodies, so use this only when needed.
+    pub fn with_body_compression(mut self, encoding: ContentEncoding) -> Self {
+        self.body_compression = Some(encoding);
+        self
+    }
+
+    /// Sends the cookie with this request, together with the cookies from the cookie store for the request URL.
+    ///
+    /// The cookie store itself is not modified. If the store (or a custom `Cookie` header) already has a cookie with the same name,
+    /// its value is replaced for this request. The extra cookies are sent after the stored ones, in the order they were added.
+    pub fn with_extra_cookie(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_cookies.push((name.into(), value.into()));
+        self
+    }
+
+    /// Turns the responses with a 4xx or 5xx status code into [`ErrorType::HttpStatus`](crate::impit::ErrorType::HttpStatus) errors,
+    /// carrying the status code, the final URL and the beginning of the response body
+    /// (up to [`MAX_ERROR_BODY_SIZE`](crate::impit::MAX_ERROR_BODY_SIZE) bytes, the rest is discarded).
+    ///
+    /// The status is checked on the final response, i.e. after following the redirects (and meta refreshes).
+    pub fn with_error_for_status(mut self, error_for_status: bool) -> Self {
+        self.error_for_status = error_for_status;
+        self.explicit_flags.error_for_status = true;
+        self
+    }
+
+    /// Authenticates the request with the HTTP Digest authentication (RFC 7616).
+    ///
+    /// The request is sent without credentials first. If the server responds with `401 Unauthorized` and a `WWW-Authenticate: Digest` challenge,
+    /// the request is sent again with the computed `Authorization` header. The `MD5`, `MD5-sess`, `SHA-256` and `SHA-256-sess` algorithms
+    /// and the `auth` and `auth-int` qualities of protection are supported.
+    ///
+    /// Requests with a streamed body (see [`RequestOptions::with_chunked_body`]) can't be resent, so their `401` response is returned as it is.
+    pub fn with_digest_auth(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.digest_auth = Some((username.into(), password.into()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_request_flags_override_the_defaults() {
+        let defaults = RequestOptions::default()
+            .with_error_for_status(true)
+            .with_follow_meta_refresh(true)
+            .with_connection_close();
+
+        let merged = RequestOptions::default()
+            .with_error_for_status(false)
+            .merged_with(&defaults);
+
+        assert!(!merged.error_for_status);
+        assert!(merged.follow_meta_refresh);
+        assert!(merged.connection_close);
+        assert!(!merged.chunked);
+    }
+
+    #[test]
+    fn unset_flags_keep_the_defaults_and_explicit_false_turns_them_off() {
+        let defaults = RequestOptions::default().with_error_for_status(true);
+
+        let unset = RequestOptions::default().merged_with(&defaults);
+        assert!(unset.error_for_status);
+
+        // Assigning the field directly doesn't mark it as explicit.
+        let assigned = RequestOptions {
+            error_for_status: false,
+            ..Default::default()
+        }
+        .merged_with(&defaults);
+        assert!(assigned.error_for_status);
+
+        let explicit = RequestOptions::default()
+            .with_error_for_status(true)
+            .with_error_for_status(false)
+            .merged_with(&defaults);
+        assert!(!explicit.error_for_status);
+    }
+
+    #[test]
+    fn explicit_flags_are_kept_without_defaults() {
+        let options = RequestOptions::default().with_follow_meta_refresh(false);
+
+        let merged = options.clone().merged_with(&RequestOptions::default());
+        assert!(!merged.follow_meta_refresh);
+        assert_eq!(merged.explicit_flags, options.explicit_flags);
+    }
+
+    #[test]
+    fn per_request_headers_replace_the_defaults_case_insensitively() {
+        let mut defaults = RequestOptions::default();
+        defaults
+            .headers
+            .insert("X-Api-Key".to_string(), "default".to_string());
+        defaults
+            .headers
+            .insert("X-Client".to_string(), "impit".to_string());
+
+        let mut options = RequestOptions::default();
+        options
+            .headers
+            .insert("x-api-key".to_string(), "override".to_string());
+
+        let merged = options.merged_with(&defaults);
+
+        assert_eq!(merged.headers.len(), 2);
+        assert_eq!(merged.headers["x-api-key"], "override");
+        assert_eq!(merged.headers["X-Client"], "impit");
+    }
+
+    #[test]
+    fn resource_types_map_to_the_sec_fetch_values() {
+        let image = ResourceType::Image.sec_fetch();
+        assert_eq!(
+            (
+                image.site.as_str(),
+                image.mode.as_str(),
+                image.dest.as_str()
+            ),
+            ("same-origin", "no-cors", "image")
+        );
+        assert!(!image.user);
+
+        let fetch = ResourceType::Fetch.sec_fetch();
+        assert_eq!(
+            (fetch.mode.as_str(), fetch.dest.as_str()),
+            ("cors", "empty")
+        );
+
+        let document = ResourceType::Document.sec_fetch();
+        assert_eq!(
+            (
+                document.site.as_str(),
+                document.mode.as_str(),
+                document.dest.as_str()
+            ),
+            ("none", "navigate", "document")
+        );
+        assert!(document.user);
+    }
 }
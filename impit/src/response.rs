@@ -0,0 +1,847 @@
+use std::{future::Future, io::Read, time::SystemTime};
+
+use bytes::Bytes;
+use futures_util::{stream, Stream};
+use http_body_util::BodyExt;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+        ETAG, LAST_MODIFIED, LINK, LOCATION,
+    },
+    Body, Response, StatusCode, Version,
+};
+use rustls::{CipherSuite, ProtocolVersion};
+use url::Url;
+
+use crate::{
+    impit::{ErrorType, FinalUrl, NewConnection, UsedHttp3},
+    response_parsing,
+};
+
+/// Extension methods for the [`reqwest::Response`] returned by the [`Impit`](crate::impit::Impit) methods.
+///
+/// ### Example
+/// ```rust
+/// use impit::response::ResponseExt;
+///
+/// let response = impit.post("https://example.com/grpc".to_string(), None, None).await?;
+/// let (body, trailers) = response.bytes_with_trailers().await?;
+///
+/// println!("{:?}", trailers.get("grpc-status"));
+/// ```
+pub trait ResponseExt {
+    /// Returns the final URL of the response, i.e. the URL of the last request made after following all redirects.
+    ///
+    /// Unlike [`Response::url`], this is always the user-facing URL, regardless of how `impit` handles redirects
+    /// or connection overrides (e.g. [`RequestOptions::with_host_header`](crate::request::RequestOptions::with_host_header)) internally.
+    fn final_url(&self) -> &Url;
+
+    /// Returns `true` if the response was received over HTTP/3.
+    ///
+    /// Since `impit` might fall back from HTTP/3 to TCP-based HTTP, this can be used to verify that the HTTP/3 negotiation works.
+    fn used_http3(&self) -> bool;
+
+    /// Returns `true` if the response is a redirect (`301`, `302`, `303`, `307` or `308`), e.g. with [`RedirectBehavior::ManualRedirect`](crate::impit::RedirectBehavior::ManualRedirect).
+    fn is_redirect(&self) -> bool;
+
+    /// Returns the `Location` header resolved against the URL of the response, so relative redirects (e.g. `/login`) result in absolute URLs.
+    ///
+    /// Returns `None` if the header is missing or invalid.
+    fn location(&self) -> Option<Url>;
+
+    /// Returns `true` if the request was sent over an existing (pooled) connection, `false` if a new connection was established for it.
+    ///
+    /// The new connections are counted by the connector, so this works for plain HTTP and resumed TLS sessions alike.
+    /// `None` for the HTTP/3 responses and the ones from a custom transport. See also [`Impit::tls_handshakes`](crate::impit::Impit::tls_handshakes).
+    fn reused_connection(&self) -> Option<bool>;
+
+    /// Returns the details of the TLS connection the response was received over, e.g. for checking that TLS 1.3 was negotiated.
+    ///
+    /// Returns `None` for plain HTTP responses and for responses from a custom [`Transport`](crate::transport::Transport).
+    fn tls_details(&self) -> Option<&TlsDetails>;
+
+    /// Returns the filename for saving the response body, e.g. when downloading files.
+    ///
+    /// This is the filename from the `Content-Disposition` header (see [`content_disposition_filename`](crate::utils::content_disposition_filename)),
+    /// or the last segment of the [`ResponseExt::final_url`] path. The filename is sanitized, so it's safe to join with a directory path.
+    fn suggested_filename(&self) -> Option<String>;
+
+    /// Returns the response headers as `(name, value)` pairs, as close to the order the server sent them as possible.
+    ///
+    /// This is a best-effort view - the headers are parsed into a [`HeaderMap`] by `hyper` before `impit` sees them, so:
+    /// - the names are lowercased (on HTTP/2 and HTTP/3, the names are lowercase on the wire anyway),
+    /// - the headers are in the order of the first occurrence of each name, with repeated headers (e.g. `Set-Cookie`) grouped together,
+    /// - with automatic decompression, the `Content-Encoding` and `Content-Length` headers of compressed responses are missing.
+    ///
+    /// Values that aren't valid UTF-8 are converted lossily.
+    fn raw_response_headers(&self) -> Vec<(String, String)>;
+
+    /// Reads the whole response body, together with the trailing headers (trailers) sent after it.
+    ///
+    /// If the server didn't send any trailers, the returned `HeaderMap` is empty.
+    /// Note that trailers are only available for responses that aren't automatically decompressed.
+    ///
+    /// Fails with [`ErrorType::IncompleteBody`] if the body is shorter than the declared `Content-Length`.
+    fn bytes_with_trailers(
+        self,
+    ) -> impl Future<Output = Result<(Bytes, HeaderMap), ErrorType>> + Send;
+
+    /// Reads the whole response body as text, replacing the invalid byte sequences with the `U+FFFD` replacement character.
+    ///
+    /// The body is decoded with the charset from the `Content-Type` header, or the detected one (see [`decode`](crate::utils::decode)),
+    /// like in [`Impit::get_text`](crate::impit::Impit::get_text).
+    fn text_lossy(self) -> impl Future<Output = Result<String, ErrorType>> + Send;
+
+    /// Like [`ResponseExt::text_lossy`], but fails with [`ErrorType::DecodingError`] if the body contains invalid byte sequences.
+    fn text_strict(self) -> impl Future<Output = Result<String, ErrorType>> + Send;
+
+    /// Reads the whole response body and parses it as JSON, without a target type.
+    ///
+    /// Useful for exploring APIs. Invalid JSON results in the [`ErrorType::DeserializationError`] error.
+    fn json_value(self) -> impl Future<Output = Result<serde_json::Value, ErrorType>> + Send;
+
+    /// Like [`Response::bytes_stream`], but the stream ends with [`ErrorType::IncompleteBody`]
+    /// if fewer bytes than the declared `Content-Length` were received.
+    ///
+    /// The length can only be checked for responses that aren't automatically decompressed,
+    /// as `reqwest` drops the `Content-Length` header of the decompressed responses.
+    fn checked_bytes_stream(self) -> impl Stream<Item = Result<Bytes, ErrorType>> + Send;
+
+    /// Consumes the response and returns its body as a stream of chunks, e.g. for piping it to another client.
+    ///
+    /// The [`Impit`](crate::impit::Impit) methods return as soon as the response headers are received, so [`Response::status`]
+    /// and [`Response::headers`] can be read (and forwarded) before any of the body is downloaded
+    /// (except for the HTML responses buffered by [`RequestOptions::with_follow_meta_refresh`](crate::request::RequestOptions::with_follow_meta_refresh)).
+    /// The stream errors are mapped to [`ErrorType`]s, the same way as in [`ResponseExt::checked_bytes_stream`].
+    ///
+    /// ### Example
+    /// ```rust
+    /// let response = impit.get("https://example.com/large-file".to_string(), None).await?;
+    /// println!("{} {:?}", response.status(), response.headers());
+    ///
+    /// let mut body = Box::pin(response.into_body_stream());
+    /// while let Some(chunk) = body.next().await {
+    ///     client.write_all(&chunk?).await?;
+    /// }
+    /// ```
+    fn into_body_stream(self) -> impl Stream<Item = Result<Bytes, ErrorType>> + Send;
+}
+
+impl ResponseExt for Response {
+    fn final_url(&self) -> &Url {
+        self.extensions()
+            .get::<FinalUrl>()
+            .map(|final_url| &final_url.0)
+            .unwrap_or(self.url())
+    }
+
+    fn used_http3(&self) -> bool {
+        self.extensions()
+            .get::<UsedHttp3>()
+            .map(|used_http3| used_http3.0)
+            .unwrap_or(self.version() == Version::HTTP_3)
+    }
+
+    fn is_redirect(&self) -> bool {
+        matches!(
+            self.status(),
+            StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT
+        )
+    }
+
+    fn location(&self) -> Option<Url> {
+        let location = self.headers().get(LOCATION)?.to_str().ok()?;
+
+        self.final_url().join(location).ok()
+    }
+
+    fn reused_connection(&self) -> Option<bool> {
+        self.extensions()
+            .get::<NewConnection>()
+            .map(|new_connection| !new_connection.0)
+    }
+
+    fn tls_details(&self) -> Option<&TlsDetails> {
+        self.extensions().get::<TlsDetails>()
+    }
+
+    fn suggested_filename(&self) -> Option<String> {
+        self.headers()
+            .get(CONTENT_DISPOSITION)
+            .and_then(|value| {
+                response_parsing::content_disposition_filename(&String::from_utf8_lossy(
+                    value.as_bytes(),
+                ))
+            })
+            .or_else(|| response_parsing::url_filename(self.final_url()))
+    }
+
+    fn raw_response_headers(&self) -> Vec<(String, String)> {
+        self.headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    fn bytes_with_trailers(
+        self,
+    ) -> impl Future<Output = Result<(Bytes, HeaderMap), ErrorType>> + Send {
+        async move {
+            let expected_length = self.content_length();
+            collect_body(Body::from(self), expected_length).await
+        }
+    }
+
+    fn text_lossy(self) -> impl Future<Output = Result<String, ErrorType>> + Send {
+        async move {
+            let encoding = body_encoding(&self);
+            let (body, _) = self.bytes_with_trailers().await?;
+            Ok(response_parsing::decode(&body, encoding))
+        }
+    }
+
+    fn text_strict(self) -> impl Future<Output = Result<String, ErrorType>> + Send {
+        async move {
+            let encoding = body_encoding(&self);
+            let (body, _) = self.bytes_with_trailers().await?;
+            response_parsing::decode_strict(&body, encoding)
+        }
+    }
+
+    fn json_value(self) -> impl Future<Output = Result<serde_json::Value, ErrorType>> + Send {
+        async move {
+            let (body, _) = self.bytes_with_trailers().await?;
+            serde_json::from_slice(&body).map_err(ErrorType::DeserializationError)
+        }
+    }
+
+    fn checked_bytes_stream(self) -> impl Stream<Item = Result<Bytes, ErrorType>> + Send {
+        let expected_length = self.content_length();
+
+        stream::unfold(
+            (Body::from(self), 0u64, false),
+            move |(mut body, mut received, done)| async move {
+                if done {
+                    return None;
+                }
+
+                loop {
+                    match body.frame().await {
+                        Some(Ok(frame)) => {
+                            if let Ok(data) = frame.into_data() {
+                                received += data.len() as u64;
+                                return Some((Ok(data), (body, received, false)));
+                            }
+                        }
+                        Some(Err(e)) => {
+                            let error = check_body_length(expected_length, received)
+                                .err()
+                                .unwrap_or(ErrorType::RequestError(e));
+                            return Some((Err(error), (body, received, true)));
+                        }
+                        None => {
+                            return check_body_length(expected_length, received)
+                                .err()
+                                .map(|error| (Err(error), (body, received, true)));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    fn into_body_stream(self) -> impl Stream<Item = Result<Bytes, ErrorType>> + Send {
+        self.checked_bytes_stream()
+    }
+}
+
+/// Returns the encoding declared in the `Content-Type` header of the response.
+fn body_encoding(response: &Response) -> Option<encoding::EncodingRef> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(response_parsing::content_type_encoding)
+}
+
+/// Reads at most `limit` bytes of the response body. Returns `None` if reading the body fails.
+pub(crate) async fn read_body_prefix(mut response: Response, limit: usize) -> Option<Bytes> {
+    let mut body = vec![];
+
+    while body.len() < limit {
+        match response.chunk().await.ok()? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+
+    body.truncate(limit);
+    Some(Bytes::from(body))
+}
+
+/// Decodes the bodies with multiple stacked content encodings (e.g. `Content-Encoding: gzip, br`), which `reqwest` passes through as they are.
+///
+/// The encodings are undone in the reverse order of the header (the last listed encoding was applied last), and the
+/// `Content-Encoding` and `Content-Length` headers are removed, like for the bodies `reqwest` decodes. The body is buffered.
+/// Responses with a single encoding are returned unchanged.
+pub(crate) async fn decode_stacked_encodings(response: Response) -> Result<Response, ErrorType> {
+    let encodings: Vec<String> = response
+        .headers()
+        .get_all(CONTENT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|encoding| encoding.trim().to_ascii_lowercase())
+        .filter(|encoding| !encoding.is_empty() && encoding != "identity")
+        .collect();
+
+    if encodings.len() < 2 {
+        return Ok(response);
+    }
+
+    let expected_length = response.content_length();
+    let (mut parts, body) = http::Response::<Body>::from(response).into_parts();
+    let (mut body, _) = collect_body(body, expected_length).await?;
+
+    for encoding in encodings.iter().rev() {
+        body = decode_content(&body, encoding)
+            .map_err(|e| ErrorType::ContentDecodingError(format!("{encoding}: {e}")))?
+            .into();
+    }
+
+    parts.headers.remove(CONTENT_ENCODING);
+    parts.headers.remove(CONTENT_LENGTH);
+
+    Ok(Response::from(http::Response::from_parts(
+        parts,
+        Body::from(body),
+    )))
+}
+
+/// Decodes a body compressed with a single content `encoding`.
+fn decode_content(body: &[u8], encoding: &str) -> std::io::Result<Vec<u8>> {
+    let mut decoded = vec![];
+
+    match encoding {
+        #[cfg(feature = "gzip")]
+        "gzip" | "x-gzip" => flate2::read::MultiGzDecoder::new(body).read_to_end(&mut decoded)?,
+        // HTTP `deflate` is the zlib format.
+        #[cfg(feature = "deflate")]
+        "deflate" => flate2::read::ZlibDecoder::new(body).read_to_end(&mut decoded)?,
+        #[cfg(feature = "brotli")]
+        "br" => brotli_decompressor::Decompressor::new(body, 4096).read_to_end(&mut decoded)?,
+        #[cfg(feature = "zstd")]
+        "zstd" => zstd::stream::read::Decoder::new(body)?.read_to_end(&mut decoded)?,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "unsupported content encoding",
+            ))
+        }
+    };
+
+    Ok(decoded)
+}
+
+/// Returns the [`ErrorType::IncompleteBody`] error if fewer than `expected_length` bytes were received.
+fn check_body_length(expected_length: Option<u64>, received: u64) -> Result<(), ErrorType> {
+    match expected_length {
+        Some(expected) if received < expected => {
+            Err(ErrorType::IncompleteBody { expected, received })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads the whole body and its trailers, checking the body length against `expected_length` (the declared `Content-Length`).
+///
+/// A connection dropped mid-body is reported as [`ErrorType::IncompleteBody`] rather than a generic body error.
+pub(crate) async fn collect_body(
+    mut body: Body,
+    expected_length: Option<u64>,
+) -> Result<(Bytes, HeaderMap), ErrorType> {
+    let mut data = vec![];
+    let mut trailers = HeaderMap::new();
+
+    while let Some(frame) = body.frame().await {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                check_body_length(expected_length, data.len() as u64)?;
+                return Err(ErrorType::RequestError(e));
+            }
+        };
+
+        match frame.into_data() {
+            Ok(chunk) => data.extend_from_slice(&chunk),
+            Err(frame) => {
+                if let Ok(frame_trailers) = frame.into_trailers() {
+                    trailers.extend(frame_trailers);
+                }
+            }
+        }
+    }
+
+    check_body_length(expected_length, data.len() as u64)?;
+
+    Ok((Bytes::from(data), trailers))
+}
+
+/// A response body decoded according to its `Content-Type`, as returned by [`Impit::fetch_typed`](crate::impit::Impit::fetch_typed).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedBody {
+    /// `text/html` and `application/xhtml+xml` documents.
+    Html(String),
+    /// `application/json` and the `+json` types (e.g. `application/ld+json`).
+    Json(serde_json::Value),
+    /// `application/xml`, `text/xml` and the `+xml` types (e.g. `application/rss+xml`).
+    Xml(String),
+    /// Other `text/*` types, e.g. `text/plain` or `text/csv`.
+    Text(String),
+    /// Everything else, e.g. images or archives.
+    Binary(Bytes),
+}
+
+impl TypedBody {
+    /// Decodes the `body` according to the `content_type`. Without the `Content-Type` header, the type is sniffed from the body.
+    ///
+    /// The text is decoded with the declared charset, or the detected one (see [`decode`](crate::utils::decode)).
+    /// Invalid JSON results in the [`ErrorType::DeserializationError`] error.
+    pub(crate) fn decode(content_type: Option<&str>, body: Bytes) -> Result<Self, ErrorType> {
+        let encoding = content_type.and_then(response_parsing::content_type_encoding);
+        let text = |body: &[u8]| response_parsing::decode(body, encoding);
+
+        let essence = match content_type {
+            Some(content_type) => content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+            None => sniff_content_type(&body).to_string(),
+        };
+
+        Ok(match essence.as_str() {
+            "text/html" | "application/xhtml+xml" => TypedBody::Html(text(&body)),
+            "application/json" => TypedBody::Json(
+                serde_json::from_slice(&body).map_err(ErrorType::DeserializationError)?,
+            ),
+            json if json.ends_with("+json") => TypedBody::Json(
+                serde_json::from_slice(&body).map_err(ErrorType::DeserializationError)?,
+            ),
+            "application/xml" | "text/xml" => TypedBody::Xml(text(&body)),
+            xml if xml.ends_with("+xml") => TypedBody::Xml(text(&body)),
+            text_type if text_type.starts_with("text/") => TypedBody::Text(text(&body)),
+            _ => TypedBody::Binary(body),
+        })
+    }
+}
+
+/// Guesses the MIME type of a body without the `Content-Type` header from its first bytes.
+fn sniff_content_type(body: &[u8]) -> &'static str {
+    let start = body
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(body.len());
+    let prefix = body[start..body.len().min(start + 64)].to_ascii_lowercase();
+
+    if prefix.starts_with(b"<!doctype html") || prefix.starts_with(b"<html") {
+        "text/html"
+    } else if prefix.starts_with(b"<?xml") {
+        "application/xml"
+    } else if (prefix.starts_with(b"{") || prefix.starts_with(b"["))
+        && serde_json::from_slice::<serde_json::Value>(body).is_ok()
+    {
+        "application/json"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// The negotiated parameters of a TLS connection, as returned by [`ResponseExt::tls_details`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsDetails {
+    /// The negotiated protocol version, e.g. [`ProtocolVersion::TLSv1_3`].
+    pub version: ProtocolVersion,
+    /// The negotiated cipher suite.
+    ///
+    /// `rustls` doesn't report the cipher suite through `reqwest`, so it's inferred from the offered cipher suites and the length
+    /// of the handshake secret, recorded for the connection.
+    /// This is `None` if the inference is ambiguous, e.g. for TLS 1.3 with both `TLS13_AES_128_GCM_SHA256` and `TLS13_CHACHA20_POLY1305_SHA256`
+    /// offered (like the browsers do). Use [`ImpitBuilder::with_cipher_suites`](crate::impit::ImpitBuilder::with_cipher_suites) to pin the suite.
+    pub cipher_suite: Option<CipherSuite>,
+    /// The DER-encoded server (end-entity) certificate.
+    pub peer_certificate: Option<Vec<u8>>,
+    /// The DER-encoded certificate chain sent by the server, starting with the end-entity certificate.
+    pub peer_certificate_chain: Vec<Vec<u8>>,
+}
+
+/// The metadata of a remote resource, as returned by [`Impit::head_info`](crate::impit::Impit::head_info).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceInfo {
+    /// The parsed `Content-Length` header.
+    ///
+    /// Note that with automatic decompression enabled, the header is removed from compressed responses,
+    /// see [`ImpitBuilder::with_auto_decompress`](crate::impit::ImpitBuilder::with_auto_decompress).
+    pub content_length: Option<u64>,
+    /// The `Content-Type` header, e.g. `text/html; charset=utf-8`.
+    pub content_type: Option<String>,
+    /// The parsed `Last-Modified` header.
+    pub last_modified: Option<SystemTime>,
+    /// The `ETag` header, including the quotes (and the `W/` prefix for weak validators).
+    pub etag: Option<String>,
+    /// The final URL of the resource, after following all redirects.
+    pub final_url: Url,
+}
+
+impl From<&Response> for ResourceInfo {
+    fn from(response: &Response) -> Self {
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+
+        ResourceInfo {
+            content_length: header(CONTENT_LENGTH).and_then(|value| value.parse().ok()),
+            content_type: header(CONTENT_TYPE),
+            last_modified: header(LAST_MODIFIED)
+                .and_then(|value| httpdate::parse_http_date(&value).ok()),
+            etag: header(ETAG),
+            final_url: response.final_url().clone(),
+        }
+    }
+}
+
+/// Returns the target of the `rel="next"` link from the RFC 8288 `Link` headers, resolved against the `base` URL.
+pub(crate) fn next_link(headers: &HeaderMap, base: &Url) -> Option<Url> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(split_links)
+        .find_map(|link| {
+            let (target, params) = link.trim().strip_prefix('<')?.split_once('>')?;
+
+            let is_next = params.split(';').any(|param| {
+                let Some((name, value)) = param.split_once('=') else {
+                    return false;
+                };
+
+                name.trim().eq_ignore_ascii_case("rel")
+                    && value
+                        .trim()
+                        .trim_matches('"')
+                        .split_ascii_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("next"))
+            });
+
+            if is_next {
+                base.join(target.trim()).ok()
+            } else {
+                None
+            }
+        })
+}
+
+/// Splits a `Link` header value into the individual links. Commas inside the `<...>` targets and quoted parameters don't split.
+fn split_links(value: &str) -> Vec<&str> {
+    let mut links = vec![];
+    let mut start = 0;
+    let mut in_target = false;
+    let mut in_quotes = false;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '<' if !in_quotes => in_target = true,
+            '>' if !in_quotes => in_target = false,
+            '"' if !in_target => in_quotes = !in_quotes,
+            ',' if !in_target && !in_quotes => {
+                links.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    links.push(&value[start..]);
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: impl Into<Body>) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+
+        Response::from(builder.body(body.into()).unwrap())
+    }
+
+    fn with_final_url(mut response: Response, url: &str) -> Response {
+        response
+            .extensions_mut()
+            .insert(FinalUrl(Url::parse(url).unwrap()));
+        response
+    }
+
+    #[test]
+    fn redirects_resolve_the_location_against_the_final_url() {
+        let redirect = with_final_url(
+            response(302, &[("location", "../login?next=%2F")], ""),
+            "https://example.com/app/page",
+        );
+
+        assert!(redirect.is_redirect());
+        assert_eq!(
+            redirect.final_url().as_str(),
+            "https://example.com/app/page"
+        );
+        assert_eq!(
+            redirect.location().unwrap().as_str(),
+            "https://example.com/login?next=%2F"
+        );
+
+        assert!(!response(200, &[], "").is_redirect());
+        assert!(!response(304, &[], "").is_redirect());
+        assert_eq!(response(301, &[], "").location(), None);
+    }
+
+    #[test]
+    fn extensions_describe_the_connection() {
+        let mut response = response(200, &[], "");
+        assert_eq!(response.reused_connection(), None);
+        assert!(!response.used_http3());
+        assert!(response.tls_details().is_none());
+
+        response.extensions_mut().insert(NewConnection(false));
+        response.extensions_mut().insert(UsedHttp3(true));
+        assert_eq!(response.reused_connection(), Some(true));
+        assert!(response.used_http3());
+    }
+
+    #[test]
+    fn suggested_filenames_prefer_the_content_disposition() {
+        let download = with_final_url(
+            response(
+                200,
+                &[("content-disposition", "attachment; filename=\"report.pdf\"")],
+                "",
+            ),
+            "https://example.com/files/download",
+        );
+        assert_eq!(download.suggested_filename().as_deref(), Some("report.pdf"));
+
+        let download = with_final_url(
+            response(200, &[], ""),
+            "https://example.com/files/archive.zip?token=1",
+        );
+        assert_eq!(
+            download.suggested_filename().as_deref(),
+            Some("archive.zip")
+        );
+    }
+
+    #[test]
+    fn raw_response_headers_keep_the_repeated_headers() {
+        let response = response(
+            200,
+            &[
+                ("Content-Type", "text/plain"),
+                ("Set-Cookie", "a=1"),
+                ("Set-Cookie", "b=2"),
+            ],
+            "",
+        );
+
+        assert_eq!(
+            response.raw_response_headers(),
+            [
+                ("content-type", "text/plain"),
+                ("set-cookie", "a=1"),
+                ("set-cookie", "b=2"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn text_is_decoded_with_the_declared_charset() {
+        let latin1 = response(
+            200,
+            &[("content-type", "text/plain; charset=iso-8859-1")],
+            vec![b'c', b'a', b'f', 0xe9],
+        );
+        assert_eq!(latin1.text_lossy().await.unwrap(), "café");
+
+        let invalid = response(
+            200,
+            &[("content-type", "text/plain; charset=utf-8")],
+            vec![b'a', 0xff],
+        );
+        assert_eq!(invalid.text_lossy().await.unwrap(), "a\u{fffd}");
+
+        let invalid = response(
+            200,
+            &[("content-type", "text/plain; charset=utf-8")],
+            vec![b'a', 0xff],
+        );
+        assert!(matches!(
+            invalid.text_strict().await,
+            Err(ErrorType::DecodingError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn json_values_are_parsed_without_a_target_type() {
+        let value = response(200, &[], r#"{"items": [1, 2]}"#)
+            .json_value()
+            .await
+            .unwrap();
+        assert_eq!(value["items"][1], 2);
+
+        assert!(matches!(
+            response(200, &[], "{").json_value().await,
+            Err(ErrorType::DeserializationError(_))
+        ));
+    }
+
+    #[test]
+    fn body_lengths_are_checked_against_the_content_length() {
+        assert!(check_body_length(None, 0).is_ok());
+        assert!(check_body_length(Some(5), 5).is_ok());
+        assert!(matches!(
+            check_body_length(Some(10), 4),
+            Err(ErrorType::IncompleteBody {
+                expected: 10,
+                received: 4
+            })
+        ));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn stacked_encodings_are_decoded_in_reverse() {
+        use std::io::Write;
+
+        let gzip = |data: &[u8]| {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        let response = response(
+            200,
+            &[("content-encoding", "gzip, gzip")],
+            gzip(&gzip(b"hello")),
+        );
+        let response = decode_stacked_encodings(response).await.unwrap();
+
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(response.bytes().await.unwrap(), "hello");
+    }
+
+    #[test]
+    fn typed_bodies_follow_the_content_type() {
+        let decode = |content_type, body: &str| {
+            TypedBody::decode(content_type, Bytes::from(body.to_owned())).unwrap()
+        };
+
+        assert_eq!(
+            decode(Some("text/html; charset=utf-8"), "<p>hi</p>"),
+            TypedBody::Html("<p>hi</p>".to_string())
+        );
+        assert_eq!(
+            decode(Some("application/ld+json"), "[1]"),
+            TypedBody::Json(serde_json::json!([1]))
+        );
+        assert_eq!(
+            decode(Some("application/rss+xml"), "<rss/>"),
+            TypedBody::Xml("<rss/>".to_string())
+        );
+        assert_eq!(
+            decode(Some("text/csv"), "a,b"),
+            TypedBody::Text("a,b".to_string())
+        );
+        assert_eq!(
+            decode(Some("image/png"), "png"),
+            TypedBody::Binary(Bytes::from("png"))
+        );
+        assert!(TypedBody::decode(Some("application/json"), Bytes::from("{")).is_err());
+    }
+
+    #[test]
+    fn bodies_without_a_content_type_are_sniffed() {
+        assert_eq!(sniff_content_type(b"  <!DOCTYPE html><html>"), "text/html");
+        assert_eq!(
+            sniff_content_type(b"<?xml version=\"1.0\"?>"),
+            "application/xml"
+        );
+        assert_eq!(sniff_content_type(b"{\"a\": 1}"), "application/json");
+        assert_eq!(sniff_content_type(b"{not json"), "application/octet-stream");
+        assert_eq!(sniff_content_type(b""), "application/octet-stream");
+    }
+
+    #[test]
+    fn resource_info_is_read_from_the_headers() {
+        let response = with_final_url(
+            response(
+                200,
+                &[
+                    ("content-length", " 1024 "),
+                    ("content-type", "application/pdf"),
+                    ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                    ("etag", "W/\"abc\""),
+                ],
+                "",
+            ),
+            "https://example.com/doc.pdf",
+        );
+        let info = ResourceInfo::from(&response);
+
+        assert_eq!(info.content_length, Some(1024));
+        assert_eq!(info.content_type.as_deref(), Some("application/pdf"));
+        assert_eq!(
+            info.last_modified,
+            Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1445412480))
+        );
+        assert_eq!(info.etag.as_deref(), Some("W/\"abc\""));
+        assert_eq!(info.final_url.as_str(), "https://example.com/doc.pdf");
+    }
+
+    #[test]
+    fn next_links_are_found_in_the_link_headers() {
+        let base = Url::parse("https://api.example.com/items?page=1").unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append(
+            LINK,
+            r#"</items?page=1&sort=a,b>; rel="prev first", <https://api.example.com/items?page=3>; rel="last""#
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(next_link(&headers, &base), None);
+
+        headers.append(
+            LINK,
+            r#"</items?page=2>; title="a, b"; REL="next""#.parse().unwrap(),
+        );
+        assert_eq!(
+            next_link(&headers, &base).unwrap().as_str(),
+            "https://api.example.com/items?page=2"
+        );
+    }
+}
@@ -1,4 +1,9 @@
+use std::time::Duration;
+
 use encoding::Encoding;
+use url::Url;
+
+use crate::impit::ErrorType;
 
 /// Implements the BOM sniffing algorithm to detect the encoding of the response.
 /// If the BOM sniffing algorithm fails, the function returns `None`.
@@ -89,19 +94,394 @@ fn prescan_bytestream(bytes: &[u8]) -> Option<encoding::EncodingRef> {
 /// assert_eq!(string, "žluťoučký kůň"); // The function uses the Windows-1250 encoding.
 /// ```
 pub fn decode(bytes: &[u8], encoding_prior_knowledge: Option<encoding::EncodingRef>) -> String {
-    let mut encoding: encoding::EncodingRef = encoding::all::UTF_8;
+    detect_encoding(bytes, encoding_prior_knowledge)
+        .decode(bytes, encoding::DecoderTrap::Replace)
+        .unwrap()
+}
 
-    if let Some(enc) = encoding_prior_knowledge {
-        encoding = enc;
-    } else if let Some(enc) = bom_sniffing(bytes) {
-        encoding = enc;
-    } else if let Some(enc) = prescan_bytestream(bytes) {
-        encoding = enc;
-    }
+/// Like [`decode`], but fails with [`ErrorType::DecodingError`] instead of replacing the invalid byte sequences with `U+FFFD`.
+///
+/// ### Example
+///
+/// ```rust
+/// let bytes = vec![0x48, 0x65, 0xFF, 0x6C, 0x6F];
+///
+/// assert!(decode_strict(&bytes, None).is_err());
+/// assert_eq!(decode(&bytes, None), "He\u{FFFD}lo");
+/// ```
+pub fn decode_strict(
+    bytes: &[u8],
+    encoding_prior_knowledge: Option<encoding::EncodingRef>,
+) -> Result<String, ErrorType> {
+    let encoding = detect_encoding(bytes, encoding_prior_knowledge);
 
     encoding
-        .decode(bytes, encoding::DecoderTrap::Replace)
-        .unwrap()
+        .decode(bytes, encoding::DecoderTrap::Strict)
+        .map_err(|e| ErrorType::DecodingError(format!("{} ({})", e, encoding.name())))
+}
+
+/// Returns the provided encoding, or the one detected with the BOM sniffing and the byte stream prescanning. Defaults to UTF-8.
+fn detect_encoding(
+    bytes: &[u8],
+    encoding_prior_knowledge: Option<encoding::EncodingRef>,
+) -> encoding::EncodingRef {
+    encoding_prior_knowledge
+        .or_else(|| bom_sniffing(bytes))
+        .or_else(|| prescan_bytestream(bytes))
+        .unwrap_or(encoding::all::UTF_8)
+}
+
+/// Returns the encoding from the `charset` parameter of the `Content-Type` header value, if it's present and known.
+pub(crate) fn content_type_encoding(content_type: &str) -> Option<encoding::EncodingRef> {
+    ContentType::from(content_type).ok().and_then(Into::into)
+}
+
+/// Returns the target URL of the `<meta http-equiv="refresh" content="5; url=...">` tag in the HTML document, if there is one.
+///
+/// The returned URL might be relative. The refresh delay is ignored, and refreshes without a URL (i.e. reloads of the same page) return `None`.
+pub(crate) fn meta_refresh_url(html: &str) -> Option<String> {
+    let dom = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("meta[http-equiv]").unwrap();
+
+    let content = dom
+        .select(&selector)
+        .find(|meta| {
+            meta.value()
+                .attr("http-equiv")
+                .is_some_and(|http_equiv| http_equiv.trim().eq_ignore_ascii_case("refresh"))
+        })?
+        .value()
+        .attr("content")?;
+
+    // The content is the delay, optionally followed by `;` (or `,`) and the URL, which can be prefixed with `url=`.
+    let (_, url) = content.split_once([';', ','])?;
+    let url = url.trim();
+    let url = match url.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url=") => url[4..].trim(),
+        _ => url,
+    };
+    let url = url.trim_matches(|c| c == '\'' || c == '"').trim();
+
+    if url.is_empty() {
+        None
+    } else {
+        Some(url.to_string())
+    }
+}
+
+/// The URL metadata of an HTML document, see [`html_metadata`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlMetadata {
+    /// The `<link rel="canonical">` URL, i.e. the preferred URL of the page for deduplication.
+    pub canonical_url: Option<Url>,
+    /// The `<base href>` URL, i.e. the URL the relative links in the document are resolved against instead of the document URL.
+    pub base_href: Option<Url>,
+}
+
+impl HtmlMetadata {
+    /// Returns the URL for resolving the relative links in the document - the `<base href>` or the `document_url`.
+    pub fn base_url<'a>(&'a self, document_url: &'a Url) -> &'a Url {
+        self.base_href.as_ref().unwrap_or(document_url)
+    }
+}
+
+/// Extracts the canonical URL and the base URL from the HTML document located at `document_url` (e.g. [`ResponseExt::final_url`](crate::response::ResponseExt::final_url)).
+///
+/// Like in browsers, only the first `<base>` tag with the `href` attribute is used, and it's resolved against the `document_url`.
+/// The canonical URL is resolved against the base URL. Invalid URLs are ignored.
+///
+/// ### Example
+///
+/// ```rust
+/// let html = r#"<head><base href="/blog/"><link rel="canonical" href="post?id=1"></head>"#;
+/// let metadata = html_metadata(html, &Url::parse("https://example.com/blog/post?id=1&ref=feed")?);
+///
+/// assert_eq!(metadata.base_href.unwrap().as_str(), "https://example.com/blog/");
+/// assert_eq!(metadata.canonical_url.unwrap().as_str(), "https://example.com/blog/post?id=1");
+/// ```
+pub fn html_metadata(html: &str, document_url: &Url) -> HtmlMetadata {
+    let dom = scraper::Html::parse_document(html);
+    let base_selector = scraper::Selector::parse("base[href]").unwrap();
+    let link_selector = scraper::Selector::parse("link[rel][href]").unwrap();
+
+    let base_href = dom
+        .select(&base_selector)
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| document_url.join(href.trim()).ok());
+
+    let base_url = base_href.as_ref().unwrap_or(document_url);
+
+    // The `rel` attribute is a space-separated list of (case-insensitive) link types.
+    let canonical_url = dom
+        .select(&link_selector)
+        .find(|link| {
+            link.value().attr("rel").is_some_and(|rel| {
+                rel.split_ascii_whitespace()
+                    .any(|link_type| link_type.eq_ignore_ascii_case("canonical"))
+            })
+        })
+        .and_then(|link| link.value().attr("href"))
+        .and_then(|href| base_url.join(href.trim()).ok());
+
+    HtmlMetadata {
+        canonical_url,
+        base_href,
+    }
+}
+
+/// Returns the filename suggested by the `Content-Disposition` header value, sanitized for saving the file to disk.
+///
+/// The extended `filename*` parameter (RFC 5987, e.g. `filename*=UTF-8''na%C3%AFve.txt`) is preferred over `filename`.
+/// The directory components, control characters and the characters not allowed in Windows filenames are removed.
+/// Returns `None` if the header has no (usable) filename.
+///
+/// ### Example
+///
+/// ```rust
+/// assert_eq!(
+///     content_disposition_filename("attachment; filename=\"report.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"),
+///     Some("résumé.pdf".to_string())
+/// );
+/// assert_eq!(content_disposition_filename("attachment; filename=\"../../etc/passwd\""), Some("passwd".to_string()));
+/// ```
+pub fn content_disposition_filename(header: &str) -> Option<String> {
+    let mut filename = None;
+    let mut extended_filename = None;
+
+    // The first part is the disposition type (`attachment` or `inline`).
+    for (name, value) in header_parameters(header).into_iter().skip(1) {
+        if name.eq_ignore_ascii_case("filename*") {
+            extended_filename = decode_extended_value(&value);
+        } else if name.eq_ignore_ascii_case("filename") {
+            filename = Some(value);
+        }
+    }
+
+    extended_filename
+        .and_then(|filename| sanitize_filename(&filename))
+        .or_else(|| filename.and_then(|filename| sanitize_filename(&filename)))
+}
+
+/// Splits a header value into the `;`-separated `name=value` parameters, unquoting the quoted values.
+fn header_parameters(header: &str) -> Vec<(String, String)> {
+    let mut parameters = vec![];
+    let mut chars = header.chars().peekable();
+
+    while chars.peek().is_some() {
+        let mut name = String::new();
+        let mut value = String::new();
+        let mut has_value = false;
+
+        for c in chars.by_ref() {
+            match c {
+                '=' => {
+                    has_value = true;
+                    break;
+                }
+                ';' => break,
+                c => name.push(c),
+            }
+        }
+
+        if has_value {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            }
+
+            value.extend(chars.by_ref().take_while(|c| *c != ';'));
+        }
+
+        parameters.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    parameters
+}
+
+/// How long an alternative service without the `ma` parameter stays fresh (RFC 7838).
+const DEFAULT_ALT_SVC_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An alternative service advertised in the `Alt-Svc` header, see [`AltSvc`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AltService {
+    /// The ALPN protocol ID, e.g. `h3` (or `h3-29` for a draft version of HTTP/3).
+    pub protocol_id: String,
+    /// The host of the alternative authority, `None` if it's the same as the origin's (e.g. `h3=":443"`).
+    pub host: Option<String>,
+    /// The port of the alternative authority.
+    pub port: u16,
+    /// How long the alternative service is fresh, from the `ma` parameter (24 hours by default).
+    pub max_age: Duration,
+    /// Whether the alternative service should be kept after network changes (`persist=1`).
+    pub persist: bool,
+}
+
+/// The parsed `Alt-Svc` response header (RFC 7838).
+///
+/// ### Example
+///
+/// ```rust
+/// let alt_svc = AltSvc::parse(r#"h3=":443"; ma=86400, h3-29=":443"; ma=86400, h2="alt.example.com:8443"; persist=1"#);
+///
+/// if let AltSvc::Services(services) = alt_svc {
+///     assert_eq!(services.len(), 3);
+///     assert_eq!(services[0].protocol_id, "h3");
+///     assert_eq!(services[2].host.as_deref(), Some("alt.example.com"));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum AltSvc {
+    /// The `clear` value, which invalidates all the alternative services of the origin.
+    Clear,
+    /// The advertised alternative services, in the order of the server's preference.
+    Services(Vec<AltService>),
+}
+
+impl AltSvc {
+    /// Parses the `Alt-Svc` header value. The repeated headers can be joined with `,` before parsing.
+    ///
+    /// The invalid alternatives (e.g. without a port) are skipped, as are the unknown parameters.
+    pub fn parse(header: &str) -> AltSvc {
+        if header.trim() == "clear" {
+            return AltSvc::Clear;
+        }
+
+        AltSvc::Services(
+            split_unquoted(header, ',')
+                .iter()
+                .filter_map(|alternative| AltSvc::parse_alternative(alternative))
+                .collect(),
+        )
+    }
+
+    fn parse_alternative(alternative: &str) -> Option<AltService> {
+        let mut parameters = header_parameters(alternative).into_iter();
+
+        let (protocol_id, authority) = parameters.next()?;
+        let protocol_id = String::from_utf8(percent_decode(&protocol_id)?).ok()?;
+        let (host, port) = authority.rsplit_once(':')?;
+
+        let mut service = AltService {
+            protocol_id,
+            host: (!host.is_empty()).then(|| host.to_string()),
+            port: port.parse().ok()?,
+            max_age: DEFAULT_ALT_SVC_MAX_AGE,
+            persist: false,
+        };
+
+        for (name, value) in parameters {
+            if name.eq_ignore_ascii_case("ma") {
+                if let Ok(max_age) = value.parse() {
+                    service.max_age = Duration::from_secs(max_age);
+                }
+            } else if name.eq_ignore_ascii_case("persist") {
+                service.persist = value == "1";
+            }
+        }
+
+        Some(service)
+    }
+}
+
+/// Splits the header value on the `separator`s outside of the quoted strings.
+fn split_unquoted(header: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in header.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(header[start..i].trim());
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(header[start..].trim());
+
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Decodes the RFC 5987 extended parameter value (`charset'language'percent-encoded-value`).
+fn decode_extended_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let bytes = percent_decode(parts.next()?)?;
+
+    encoding::label::encoding_from_whatwg_label(charset)?
+        .decode(&bytes, encoding::DecoderTrap::Replace)
+        .ok()
+}
+
+/// Decodes the `%XX` escapes. Returns `None` for malformed escapes.
+pub(crate) fn percent_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+
+    let mut i = 0;
+    while i < encoded.len() {
+        let byte = match encoded[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(encoded.get(i + 1..i + 3)?).ok()?;
+                i += 2;
+                u8::from_str_radix(hex, 16).ok()?
+            }
+            byte => byte,
+        };
+        bytes.push(byte);
+        i += 1;
+    }
+
+    Some(bytes)
+}
+
+/// Returns the sanitized filename from the last segment of the URL path (e.g. `report.pdf` for `/files/report.pdf?v=2`).
+pub(crate) fn url_filename(url: &Url) -> Option<String> {
+    let segment = url
+        .path_segments()?
+        .rev()
+        .find(|segment| !segment.is_empty())?;
+    let segment = percent_decode(segment)?;
+
+    sanitize_filename(&String::from_utf8_lossy(&segment))
+}
+
+/// Makes the server-provided filename safe to use as a (single) path component.
+fn sanitize_filename(filename: &str) -> Option<String> {
+    let basename = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let mut sanitized: String = basename
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+        .collect::<String>()
+        .trim_matches(|c: char| c.is_whitespace() || c == '.')
+        .to_string();
+
+    // Most filesystems limit the filenames to 255 bytes.
+    while sanitized.len() > 255 {
+        sanitized.pop();
+    }
+
+    if sanitized.is_empty() {
+        None
+    } else {
+        Some(sanitized)
+    }
 }
 
 /// A struct that represents the contents of the `Content-Type` header.
@@ -142,3 +522,146 @@ impl From<ContentType> for Option<encoding::EncodingRef> {
         encoding::label::encoding_from_whatwg_label(val.charset.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alt_svc_services_are_parsed() {
+        let alt_svc = AltSvc::parse(
+            r#"h3=":443"; ma=3600, h3-29=":443", h2="alt.example.com:8443"; persist=1"#,
+        );
+
+        assert_eq!(
+            alt_svc,
+            AltSvc::Services(vec![
+                AltService {
+                    protocol_id: "h3".to_string(),
+                    host: None,
+                    port: 443,
+                    max_age: Duration::from_secs(3600),
+                    persist: false,
+                },
+                AltService {
+                    protocol_id: "h3-29".to_string(),
+                    host: None,
+                    port: 443,
+                    max_age: DEFAULT_ALT_SVC_MAX_AGE,
+                    persist: false,
+                },
+                AltService {
+                    protocol_id: "h2".to_string(),
+                    host: Some("alt.example.com".to_string()),
+                    port: 8443,
+                    max_age: DEFAULT_ALT_SVC_MAX_AGE,
+                    persist: true,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn alt_svc_clear_and_invalid_alternatives() {
+        assert_eq!(AltSvc::parse(" clear "), AltSvc::Clear);
+        assert_eq!(
+            AltSvc::parse(r#"h3="443", h3=":port"; ma=60"#),
+            AltSvc::Services(vec![])
+        );
+    }
+
+    #[test]
+    fn content_disposition_filenames() {
+        assert_eq!(
+            content_disposition_filename(
+                r#"attachment; filename="report.pdf"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"#
+            ),
+            Some("résumé.pdf".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename("attachment; filename=plain.txt"),
+            Some("plain.txt".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="a\"b<c>.txt""#),
+            Some("abc.txt".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename("attachment; filename=fallback.txt; filename*=UTF-8''%ZZ"),
+            Some("fallback.txt".to_string())
+        );
+    }
+
+    #[test]
+    fn content_disposition_filenames_are_sanitized() {
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="../../etc/passwd""#),
+            Some("passwd".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename=..\..\boot.ini"#),
+            Some("boot.ini".to_string())
+        );
+        assert_eq!(
+            content_disposition_filename(r#"attachment; filename="..""#),
+            None
+        );
+        assert_eq!(content_disposition_filename("inline"), None);
+    }
+
+    #[test]
+    fn meta_refresh_urls() {
+        assert_eq!(
+            meta_refresh_url(r#"<meta http-equiv="Refresh" content="5; URL='/next'">"#),
+            Some("/next".to_string())
+        );
+        assert_eq!(
+            meta_refresh_url(r#"<meta http-equiv="refresh" content="0,https://example.com/">"#),
+            Some("https://example.com/".to_string())
+        );
+        assert_eq!(
+            meta_refresh_url(r#"<meta http-equiv="refresh" content="30">"#),
+            None
+        );
+        assert_eq!(
+            meta_refresh_url(r#"<meta http-equiv="content-type" content="text/html; url=/x">"#),
+            None
+        );
+    }
+
+    #[test]
+    fn html_metadata_is_resolved_against_the_base_url() {
+        let document_url = Url::parse("https://example.com/blog/post?id=1&ref=feed").unwrap();
+
+        let metadata = html_metadata(
+            r#"<head><base href="/blog/"><base href="/other/"><link rel="canonical" href="post?id=1"></head>"#,
+            &document_url,
+        );
+
+        assert_eq!(
+            metadata.base_href.as_ref().map(Url::as_str),
+            Some("https://example.com/blog/")
+        );
+        assert_eq!(
+            metadata.canonical_url.as_ref().map(Url::as_str),
+            Some("https://example.com/blog/post?id=1")
+        );
+    }
+
+    #[test]
+    fn html_metadata_without_base() {
+        let document_url = Url::parse("https://example.com/a/b").unwrap();
+
+        let metadata = html_metadata(
+            r#"<link rel="preload" href="/style.css"><link rel="alternate CANONICAL" href="c">"#,
+            &document_url,
+        );
+
+        assert_eq!(metadata.base_href, None);
+        assert_eq!(metadata.base_url(&document_url), &document_url);
+        assert_eq!(
+            metadata.canonical_url.as_ref().map(Url::as_str),
+            Some("https://example.com/a/c")
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+/// A single event received from a `text/event-stream` response.
+///
+/// See [`Impit::open_sse`](crate::impit::Impit::open_sse) for more details.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    /// The event type (the `event` field). `None` means the default `message` type.
+    pub event: Option<String>,
+    /// The event payload. Multiple `data` fields are joined with newlines.
+    pub data: String,
+    /// The last event ID seen in the stream (the `id` field).
+    pub id: Option<String>,
+    /// The reconnection time requested by the server (the `retry` field).
+    pub retry: Option<Duration>,
+}
+
+/// An incremental parser for the `text/event-stream` format.
+///
+/// See more details at https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+#[derive(Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    bom_checked: bool,
+    event: Option<String>,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    /// Feeds a chunk of the response body to the parser and returns the events completed by it.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        if !self.bom_checked && self.buffer.len() >= 3 {
+            if self.buffer.starts_with(&[0xEF, 0xBB, 0xBF]) {
+                self.buffer.drain(..3);
+            }
+            self.bom_checked = true;
+        }
+
+        let mut events = vec![];
+
+        while let Some(position) = self
+            .buffer
+            .iter()
+            .position(|&byte| byte == b'\n' || byte == b'\r')
+        {
+            // A trailing CR might be the first half of a CRLF sequence split between two chunks.
+            if self.buffer[position] == b'\r' && position + 1 == self.buffer.len() {
+                break;
+            }
+
+            let terminator_length =
+                if self.buffer[position] == b'\r' && self.buffer[position + 1] == b'\n' {
+                    2
+                } else {
+                    1
+                };
+
+            let line: Vec<u8> = self
+                .buffer
+                .drain(..position + terminator_length)
+                .take(position)
+                .collect();
+
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_owned()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" => {
+                if !value.contains('\0') {
+                    self.last_event_id = Some(value.to_owned());
+                }
+            }
+            "retry" => {
+                if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) {
+                    if let Ok(retry) = value.parse::<u64>() {
+                        self.retry = Some(Duration::from_millis(retry));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+
+        Some(SseEvent {
+            event,
+            data,
+            id: self.last_event_id.clone(),
+            retry: self.retry.take(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_collected_into_events() {
+        let mut parser = SseParser::default();
+
+        let events = parser.feed(
+            b"event: update\n: a comment\ndata: first\ndata:second\nid: 7\nretry: 3000\n\ndata: next\n\n",
+        );
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("update".to_string()),
+                    data: "first\nsecond".to_string(),
+                    id: Some("7".to_string()),
+                    retry: Some(Duration::from_secs(3)),
+                },
+                SseEvent {
+                    event: None,
+                    data: "next".to_string(),
+                    id: Some("7".to_string()),
+                    retry: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_are_split_across_chunks() {
+        let mut parser = SseParser::default();
+
+        assert!(parser.feed(b"data: hel").is_empty());
+        assert!(parser.feed(b"lo\r").is_empty());
+
+        let events = parser.feed(b"\n\r\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "hello");
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        let mut parser = SseParser::default();
+
+        let events = parser.feed(b"\xEF\xBB\xBFdata: x\n\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "x");
+    }
+
+    #[test]
+    fn events_without_data_are_dropped() {
+        let mut parser = SseParser::default();
+
+        assert!(parser.feed(b"event: ping\n\n").is_empty());
+
+        let events = parser.feed(b"retry: 1s\nid: a\0b\ndata: x\n\n");
+
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                data: "x".to_string(),
+                ..Default::default()
+            }]
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use sha2::{Digest, Sha256};
+
+const EXTENSION_SERVER_NAME: u16 = 0x0000;
+const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 0x000d;
+const EXTENSION_ALPN: u16 = 0x0010;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 0x002b;
+
+/// The parts of a ClientHello relevant for fingerprinting.
+struct ClientHello {
+    legacy_version: u16,
+    cipher_suites: Vec<u16>,
+    /// The extension types and their payloads, in the order they were sent.
+    extensions: Vec<(u16, Vec<u8>)>,
+}
+
+/// A minimal big-endian reader over a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.take(3)
+            .map(|bytes| usize::from_be_bytes([0, 0, 0, 0, 0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    /// Reads a vector prefixed with its (1 or 2 byte long) length.
+    fn vec(&mut self, len_bytes: usize) -> Option<Reader<'a>> {
+        let len = match len_bytes {
+            1 => self.u8()? as usize,
+            _ => self.u16()? as usize,
+        };
+
+        self.take(len).map(Reader)
+    }
+
+    fn u16_list(mut self) -> Vec<u16> {
+        let mut values = vec![];
+        while let Some(value) = self.u16() {
+            values.push(value);
+        }
+        values
+    }
+}
+
+impl ClientHello {
+    /// Parses a TLS record containing a ClientHello handshake message.
+    fn parse(record: &[u8]) -> Option<Self> {
+        let mut record = Reader(record);
+
+        // The record header - content type (handshake), legacy version and length.
+        if record.u8()? != 0x16 {
+            return None;
+        }
+        record.take(2)?;
+        let mut handshake = record.vec(2)?;
+
+        // The handshake header - message type (ClientHello) and length.
+        if handshake.u8()? != 0x01 {
+            return None;
+        }
+        let len = handshake.u24()?;
+        let mut hello = Reader(handshake.take(len)?);
+
+        let legacy_version = hello.u16()?;
+        hello.take(32)?; // random
+        hello.vec(1)?; // legacy session ID
+        let cipher_suites = hello.vec(2)?.u16_list();
+        hello.vec(1)?; // legacy compression methods
+
+        let mut extensions = vec![];
+        if let Some(mut extensions_reader) = hello.vec(2) {
+            while let Some(extension_type) = extensions_reader.u16() {
+                let payload = extensions_reader.vec(2)?;
+                extensions.push((extension_type, payload.0.to_vec()));
+            }
+        }
+
+        Some(ClientHello {
+            legacy_version,
+            cipher_suites,
+            extensions,
+        })
+    }
+
+    fn extension(&self, extension_type: u16) -> Option<Reader<'_>> {
+        self.extensions
+            .iter()
+            .find(|(t, _)| *t == extension_type)
+            .map(|(_, payload)| Reader(payload))
+    }
+}
+
+/// GREASE values (RFC 8701) are random placeholders, which are ignored in the fingerprints.
+fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+/// Returns the first 12 hex characters of the SHA-256 hash of `input`, or zeros for an empty input.
+fn truncated_hash(input: &str) -> String {
+    if input.is_empty() {
+        return "000000000000".to_string();
+    }
+
+    format!("{:x}", Sha256::digest(input.as_bytes()))[..12].to_string()
+}
+
+fn hex_list(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{:04x}", value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Returns the extension types of the ClientHello in the TLS `record`, in the order they were sent (GREASE values included).
+#[cfg(test)]
+pub(crate) fn extension_types(record: &[u8]) -> Option<Vec<u16>> {
+    let client_hello = ClientHello::parse(record)?;
+
+    Some(
+        client_hello
+            .extensions
+            .iter()
+            .map(|(extension_type, _)| *extension_type)
+            .collect(),
+    )
+}
+
+/// Returns the cipher suites of the ClientHello in the TLS `record`, in the order they were sent (GREASE values skipped).
+#[cfg(test)]
+pub(crate) fn cipher_suites(record: &[u8]) -> Option<Vec<u16>> {
+    let client_hello = ClientHello::parse(record)?;
+
+    Some(
+        client_hello
+            .cipher_suites
+            .into_iter()
+            .filter(|suite| !is_grease(*suite))
+            .collect(),
+    )
+}
+
+/// Returns the hostname of the server name (SNI) extension of the ClientHello in the TLS `record`.
+#[cfg(test)]
+pub(crate) fn server_name(record: &[u8]) -> Option<String> {
+    let client_hello = ClientHello::parse(record)?;
+    let mut names = client_hello.extension(EXTENSION_SERVER_NAME)?.vec(2)?;
+
+    // The name type (0 for hostnames) and the name.
+    if names.u8()? != 0 {
+        return None;
+    }
+    let name = names.vec(2)?;
+
+    String::from_utf8(name.0.to_vec()).ok()
+}
+
+/// Computes the JA4 fingerprint (e.g. `t13d1516h2_8daaf6152771_e5627efa2ab1`) of the ClientHello in the TLS `record`.
+///
+/// Returns `None` if the record doesn't contain a valid ClientHello.
+pub(crate) fn ja4(record: &[u8], quic: bool) -> Option<String> {
+    let client_hello = ClientHello::parse(record)?;
+
+    let version = client_hello
+        .extension(EXTENSION_SUPPORTED_VERSIONS)
+        .and_then(|mut payload| payload.vec(1))
+        .map(Reader::u16_list)
+        .and_then(|versions| versions.into_iter().filter(|v| !is_grease(*v)).max())
+        .unwrap_or(client_hello.legacy_version);
+
+    let version = match version {
+        0x0304 => "13",
+        0x0303 => "12",
+        0x0302 => "11",
+        0x0301 => "10",
+        0x0300 => "s3",
+        _ => "00",
+    };
+
+    let sni = match client_hello.extension(EXTENSION_SERVER_NAME) {
+        Some(_) => 'd',
+        None => 'i',
+    };
+
+    let alpn = client_hello
+        .extension(EXTENSION_ALPN)
+        .and_then(|mut payload| payload.vec(2)?.vec(1))
+        .map(|protocol| String::from_utf8_lossy(protocol.0).into_owned())
+        .and_then(|protocol| {
+            let first = protocol.chars().next()?;
+            let last = protocol.chars().last()?;
+            Some(format!("{first}{last}"))
+        })
+        .unwrap_or_else(|| "00".to_string());
+
+    let mut cipher_suites: Vec<u16> = client_hello
+        .cipher_suites
+        .iter()
+        .copied()
+        .filter(|suite| !is_grease(*suite))
+        .collect();
+
+    let extensions: Vec<u16> = client_hello
+        .extensions
+        .iter()
+        .map(|(extension_type, _)| *extension_type)
+        .filter(|extension_type| !is_grease(*extension_type))
+        .collect();
+
+    let part_a = format!(
+        "{}{}{}{:02}{:02}{}",
+        if quic { 'q' } else { 't' },
+        version,
+        sni,
+        cipher_suites.len().min(99),
+        extensions.len().min(99),
+        alpn
+    );
+
+    cipher_suites.sort_unstable();
+    let part_b = truncated_hash(&hex_list(&cipher_suites));
+
+    // The SNI and ALPN extensions are already represented in the first part.
+    let mut sorted_extensions: Vec<u16> = extensions
+        .into_iter()
+        .filter(|extension_type| {
+            *extension_type != EXTENSION_SERVER_NAME && *extension_type != EXTENSION_ALPN
+        })
+        .collect();
+    sorted_extensions.sort_unstable();
+
+    let signature_algorithms = client_hello
+        .extension(EXTENSION_SIGNATURE_ALGORITHMS)
+        .and_then(|mut payload| payload.vec(2))
+        .map(Reader::u16_list)
+        .unwrap_or_default();
+
+    let mut part_c = hex_list(&sorted_extensions);
+    if !signature_algorithms.is_empty() {
+        part_c.push('_');
+        part_c.push_str(&hex_list(&signature_algorithms));
+    }
+    let part_c = truncated_hash(&part_c);
+
+    Some(format!("{part_a}_{part_b}_{part_c}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a TLS record with a ClientHello offering the `cipher_suites` and `extensions`.
+    fn client_hello(
+        legacy_version: u16,
+        cipher_suites: &[u16],
+        extensions: &[(u16, Vec<u8>)],
+    ) -> Vec<u8> {
+        let mut hello = legacy_version.to_be_bytes().to_vec();
+        hello.extend_from_slice(&[0; 32]);
+        hello.push(0);
+        hello.extend_from_slice(&((cipher_suites.len() * 2) as u16).to_be_bytes());
+        for suite in cipher_suites {
+            hello.extend_from_slice(&suite.to_be_bytes());
+        }
+        hello.extend_from_slice(&[1, 0]);
+
+        let mut extensions_bytes = vec![];
+        for (extension_type, payload) in extensions {
+            extensions_bytes.extend_from_slice(&extension_type.to_be_bytes());
+            extensions_bytes.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            extensions_bytes.extend_from_slice(payload);
+        }
+        hello.extend_from_slice(&(extensions_bytes.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&extensions_bytes);
+
+        let mut handshake = vec![0x01];
+        handshake.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+        handshake.extend_from_slice(&hello);
+
+        let mut record = vec![0x16, 0x03, 0x01];
+        record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        record.extend_from_slice(&handshake);
+        record
+    }
+
+    #[test]
+    fn ja4_ignores_grease_and_sorts_the_hashed_lists() {
+        let record = client_hello(
+            0x0303,
+            &[0x0a0a, 0xc02b, 0x1301, 0x1302],
+            &[
+                (0x1a1a, vec![]),
+                (EXTENSION_SERVER_NAME, vec![0, 0]),
+                (
+                    EXTENSION_ALPN,
+                    [&[0, 12, 2][..], b"h2", &[8], b"http/1.1"].concat(),
+                ),
+                (
+                    EXTENSION_SUPPORTED_VERSIONS,
+                    vec![4, 0x2a, 0x2a, 0x03, 0x04],
+                ),
+                (
+                    EXTENSION_SIGNATURE_ALGORITHMS,
+                    vec![0, 4, 0x04, 0x03, 0x08, 0x04],
+                ),
+                (0x000a, vec![0, 2, 0x00, 0x1d]),
+            ],
+        );
+
+        assert_eq!(
+            ja4(&record, false).as_deref(),
+            Some("t13d0305h2_5559582ccdc4_fbabbea27ee8")
+        );
+        assert_eq!(
+            extension_types(&record),
+            Some(vec![0x1a1a, 0x0000, 0x0010, 0x002b, 0x000d, 0x000a])
+        );
+    }
+
+    #[test]
+    fn ja4_without_sni_and_alpn() {
+        let record = client_hello(0x0303, &[0x1301], &[(0x000a, vec![0, 2, 0x00, 0x1d])]);
+
+        assert_eq!(
+            ja4(&record, false).as_deref(),
+            Some("t12i010100_0f2cb44170f4_a8f3e973773c")
+        );
+        assert_eq!(
+            ja4(&record, true).as_deref(),
+            Some("q12i010100_0f2cb44170f4_a8f3e973773c")
+        );
+    }
+
+    #[test]
+    fn server_name_is_read_from_the_extension() {
+        let name = b"xn--bcher-kva.example";
+        let mut payload = ((name.len() + 3) as u16).to_be_bytes().to_vec();
+        payload.push(0);
+        payload.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        payload.extend_from_slice(name);
+
+        let record = client_hello(0x0303, &[0x1301], &[(EXTENSION_SERVER_NAME, payload)]);
+        assert_eq!(
+            server_name(&record).as_deref(),
+            Some("xn--bcher-kva.example")
+        );
+
+        let record = client_hello(0x0303, &[0x1301], &[(0x000a, vec![0, 2, 0x00, 0x1d])]);
+        assert_eq!(server_name(&record), None);
+    }
+
+    #[test]
+    fn ja4_rejects_other_records() {
+        let mut record = client_hello(0x0303, &[0x1301], &[]);
+
+        assert!(ja4(&record[..record.len() - 1], false).is_none());
+
+        // A ServerHello instead of the ClientHello.
+        record[5] = 0x02;
+        assert!(ja4(&record, false).is_none());
+
+        assert!(ja4(&[0x17, 0x03, 0x03, 0x00, 0x00], false).is_none());
+    }
+}
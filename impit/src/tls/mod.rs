@@ -1,15 +1,33 @@
 mod ffdhe;
+mod fingerprint;
 mod statics;
 
-use std::sync::Arc;
+pub(crate) use fingerprint::ja4;
+#[cfg(test)]
+pub(crate) use fingerprint::{cipher_suites, extension_types, server_name};
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::emulation::Browser;
+use crate::impit::{ErrorType, RevocationChecking};
+use log::warn;
 use reqwest::Version;
-use rustls::client::danger::NoVerifier;
-use rustls::client::{BrowserEmulator as RusTLSBrowser, BrowserType, EchGreaseConfig};
+use rustls::client::danger::{
+    HandshakeSignatureValid, NoVerifier, ServerCertVerified, ServerCertVerifier,
+};
+use rustls::client::{
+    BrowserEmulator as RusTLSBrowser, BrowserType, EchGreaseConfig, VerifierBuilderError,
+    WebPkiServerVerifier,
+};
 use rustls::crypto::aws_lc_rs::kx_group::{SECP256R1, SECP384R1, X25519};
-use rustls::crypto::CryptoProvider;
-use rustls::RootCertStore;
+use rustls::crypto::{aws_lc_rs, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{
+    CipherSuite, DigitallySignedStruct, ProtocolVersion, RootCertStore, SignatureScheme,
+    SupportedCipherSuite,
+};
 
 pub struct TlsConfig {}
 
@@ -17,13 +35,304 @@ impl TlsConfig {
     pub fn builder() -> TlsConfigBuilder {
         TlsConfigBuilder::default()
     }
+
+    /// Looks up a cipher suite supported by `rustls` by its IANA name (e.g. `TLS13_AES_128_GCM_SHA256`).
+    ///
+    /// The lookup is case-insensitive. Returns `None` if the cipher suite is unknown or unsupported.
+    pub fn cipher_suite_from_name(name: &str) -> Option<SupportedCipherSuite> {
+        aws_lc_rs::ALL_CIPHER_SUITES
+            .iter()
+            .find(|suite| {
+                suite
+                    .suite()
+                    .as_str()
+                    .is_some_and(|suite_name| suite_name.eq_ignore_ascii_case(name))
+            })
+            .copied()
+    }
+
+    /// Builds a certificate verifier checking the revocation status of the server certificates against the CRLs from `mode`.
+    ///
+    /// Returns `None` for [`RevocationChecking::Disabled`], i.e. when the [`TlsConfig::default_verifier`] should be used.
+    pub fn revocation_verifier(
+        mode: &RevocationChecking,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Option<Arc<WebPkiServerVerifier>>, VerifierBuilderError> {
+        let mut root_store = RootCertStore::empty();
+        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        let builder = WebPkiServerVerifier::builder_with_provider(Arc::new(root_store), provider);
+
+        let verifier = match mode {
+            RevocationChecking::Disabled => return Ok(None),
+            RevocationChecking::EndEntity(crls) => builder
+                .with_crls(crls.iter().cloned())
+                .only_check_end_entity_revocation()
+                .allow_unknown_revocation_status()
+                .build()?,
+            RevocationChecking::FullChain(crls) => {
+                builder.with_crls(crls.iter().cloned()).build()?
+            }
+        };
+
+        Ok(Some(verifier))
+    }
+
+    /// Checks whether [`TlsConfigBuilder::without_extension`] can remove the extension with the given IANA ID.
+    ///
+    /// The extensions `rustls` can be configured to omit are supported: `server_name` (0), `compress_certificate` (27),
+    /// `session_ticket` (35), `early_data` (42) and `encrypted_client_hello` (65037). `supported_versions` (43) and `key_share` (51)
+    /// are required for TLS 1.3, so removing them limits the ClientHello to TLS 1.2 (logged as a warning).
+    pub fn check_removable_extension(extension_id: u16) -> Result<(), ErrorType> {
+        match extension_id {
+            EXTENSION_SERVER_NAME
+            | EXTENSION_COMPRESS_CERTIFICATE
+            | EXTENSION_SESSION_TICKET
+            | EXTENSION_EARLY_DATA
+            | EXTENSION_ENCRYPTED_CLIENT_HELLO => Ok(()),
+            EXTENSION_SUPPORTED_VERSIONS | EXTENSION_KEY_SHARE => {
+                warn!(
+                    "The TLS extension {} is required for TLS 1.3, the ClientHello will only offer TLS 1.2.",
+                    extension_id
+                );
+                Ok(())
+            }
+            _ => Err(ErrorType::UnsupportedTlsExtension(extension_id)),
+        }
+    }
+
+    /// Builds the certificate verifier `rustls` uses by default, checking the server certificates against the `webpki` roots.
+    pub fn default_verifier(provider: Arc<CryptoProvider>) -> Arc<WebPkiServerVerifier> {
+        let mut root_store = RootCertStore::empty();
+        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        WebPkiServerVerifier::builder_with_provider(Arc::new(root_store), provider)
+            .build()
+            .expect("The verifier without CRLs should always build.")
+    }
+
+    /// Determines the negotiated cipher suite of a handshake recorded by the [`HandshakeCounter`].
+    ///
+    /// The server picks one of the `offered` cipher suites with the negotiated `version`, and the TLS 1.3 handshake secrets
+    /// are as long as the hash of the suite. Returns `None` if more than one offered suite matches,
+    /// e.g. `TLS13_AES_128_GCM_SHA256` and `TLS13_CHACHA20_POLY1305_SHA256`.
+    pub fn negotiated_cipher_suite(
+        offered: &[SupportedCipherSuite],
+        handshake: &HandshakeRecord,
+    ) -> Option<CipherSuite> {
+        let mut candidates = offered.iter().filter(|suite| {
+            suite.version().version == handshake.version
+                && suite.tls13().is_none_or(|suite| {
+                    handshake
+                        .secret_length
+                        .is_none_or(|length| suite.common.hash_provider.output_len() == length)
+                })
+        });
+
+        match (candidates.next(), candidates.next()) {
+            (Some(suite), None) => Some(suite.suite()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the TLS record with the ClientHello message the `config` sends when connecting to `server_name`.
+///
+/// The handshake is only started in memory, no connection is made.
+pub fn capture_client_hello(
+    mut config: rustls::ClientConfig,
+    server_name: &str,
+) -> Result<Vec<u8>, rustls::Error> {
+    // `reqwest` sets the ALPN protocols for TCP-based connections on its own.
+    if config.alpn_protocols.is_empty() {
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    }
+
+    let server_name = rustls::pki_types::ServerName::try_from(server_name.to_owned())
+        .map_err(|_| rustls::Error::General(format!("invalid server name `{server_name}`")))?;
+
+    let mut connection = rustls::ClientConnection::new(Arc::new(config), server_name)?;
+
+    let mut client_hello = vec![];
+    while connection.wants_write() {
+        connection
+            .write_tls(&mut client_hello)
+            .expect("writing to a Vec can't fail");
+    }
+
+    Ok(client_hello)
+}
+
+/// The parameters of the last full (not resumed) TLS handshake with a host, see [`HandshakeCounter::handshake`].
+#[derive(Debug, Clone)]
+pub struct HandshakeRecord {
+    pub version: ProtocolVersion,
+    /// The length of the TLS 1.3 client handshake secret, i.e. of the cipher suite hash.
+    pub secret_length: Option<usize>,
+    /// The certificate chain sent by the server, starting with the end-entity certificate.
+    pub certificate_chain: Vec<CertificateDer<'static>>,
+}
+
+/// Counts the TLS handshakes, i.e. the newly established TLS connections, and records the parameters of the full handshakes per host.
+///
+/// Used as the `rustls` key log, which is called on every handshake, and by the [`RecordingVerifier`] wrapping the certificate verifier.
+#[derive(Debug, Default)]
+pub struct HandshakeCounter {
+    count: AtomicU64,
+    /// The length of the last logged TLS 1.3 client handshake secret.
+    secret_length: Mutex<Option<usize>>,
+    /// The host and the certificate chain of the handshake being verified, completed by the handshake signature check.
+    pending: Mutex<Option<(String, Vec<CertificateDer<'static>>)>>,
+    handshakes: Mutex<HashMap<String, HandshakeRecord>>,
+}
+
+impl HandshakeCounter {
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the parameters of the last full handshake with `host` (the TLS server name).
+    ///
+    /// The resumed sessions skip the certificate verification, so their parameters are the ones of the original handshake.
+    pub fn handshake(&self, host: &str) -> Option<HandshakeRecord> {
+        self.handshakes.lock().unwrap().get(host).cloned()
+    }
+
+    /// Completes the pending handshake, once the server proved it owns the verified certificate.
+    fn record(&self, version: ProtocolVersion) {
+        let Some((host, certificate_chain)) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+
+        let secret_length = match version {
+            ProtocolVersion::TLSv1_3 => *self.secret_length.lock().unwrap(),
+            _ => None,
+        };
+
+        self.handshakes.lock().unwrap().insert(
+            host,
+            HandshakeRecord {
+                version,
+                secret_length,
+                certificate_chain,
+            },
+        );
+    }
+}
+
+impl rustls::KeyLog for HandshakeCounter {
+    fn log(&self, label: &str, _client_random: &[u8], secret: &[u8]) {
+        // Every TLS 1.3 handshake (resumed ones included) logs the client handshake secret once - right after the `ServerHello`,
+        // so before the certificate is verified. TLS 1.2 handshakes log the master secret.
+        match label {
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => {
+                *self.secret_length.lock().unwrap() = Some(secret.len());
+            }
+            "CLIENT_RANDOM" => {}
+            _ => return,
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Wraps the certificate verifier, recording the verified certificate chains (and the protocol versions) in the [`HandshakeCounter`].
+///
+/// `reqwest` only exposes the end-entity certificate of a connection, and `rustls` doesn't report the negotiated parameters through it.
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    handshake_counter: Arc<HandshakeCounter>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        let certificate_chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|certificate| certificate.clone().into_owned())
+            .collect();
+
+        *self.handshake_counter.pending.lock().unwrap() =
+            Some((server_name.to_str().into_owned(), certificate_chain));
+
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        let valid = self.inner.verify_tls12_signature(message, cert, dss)?;
+        self.handshake_counter.record(ProtocolVersion::TLSv1_2);
+        Ok(valid)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        let valid = self.inner.verify_tls13_signature(message, cert, dss)?;
+        self.handshake_counter.record(ProtocolVersion::TLSv1_3);
+        Ok(valid)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Returns the `rustls` browser emulator for the impersonated browser.
+fn rustls_browser(browser: Browser) -> RusTLSBrowser {
+    match browser {
+        Browser::Chrome => RusTLSBrowser {
+            browser_type: BrowserType::Chrome,
+            version: 125,
+        },
+        Browser::Firefox => RusTLSBrowser {
+            browser_type: BrowserType::Firefox,
+            version: 125,
+        },
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_COMPRESS_CERTIFICATE: u16 = 27;
+const EXTENSION_SESSION_TICKET: u16 = 35;
+const EXTENSION_EARLY_DATA: u16 = 42;
+const EXTENSION_SUPPORTED_VERSIONS: u16 = 43;
+const EXTENSION_KEY_SHARE: u16 = 51;
+const EXTENSION_ENCRYPTED_CLIENT_HELLO: u16 = 0xfe0d;
+
+#[derive(Debug, Clone)]
 pub struct TlsConfigBuilder {
     browser: Option<Browser>,
     max_http_version: Version,
     ignore_tls_errors: bool,
+    cipher_suites: Vec<SupportedCipherSuite>,
+    early_data: bool,
+    disabled_extensions: Vec<u16>,
+    revocation_checking: RevocationChecking,
+    handshake_counter: Option<Arc<HandshakeCounter>>,
+    session_resumption: bool,
 }
 
 impl Default for TlsConfigBuilder {
@@ -32,12 +341,45 @@ impl Default for TlsConfigBuilder {
             browser: None,
             max_http_version: Version::HTTP_2,
             ignore_tls_errors: false,
+            cipher_suites: vec![],
+            early_data: false,
+            disabled_extensions: vec![],
+            revocation_checking: RevocationChecking::Disabled,
+            handshake_counter: None,
+            session_resumption: true,
         }
     }
 }
 
 impl TlsConfigBuilder {
-    fn get_ech_mode(self) -> rustls::client::EchMode {
+    /// Sets the protocol versions - with the GREASE ECH extension, unless it's disabled.
+    fn with_versions(
+        &self,
+        builder: rustls::ConfigBuilder<rustls::ClientConfig, rustls::WantsVersions>,
+    ) -> rustls::ConfigBuilder<rustls::ClientConfig, rustls::WantsVerifier> {
+        // Without these, only a TLS 1.2 handshake is possible (ECH needs TLS 1.3 too).
+        if self
+            .disabled_extensions
+            .contains(&EXTENSION_SUPPORTED_VERSIONS)
+            || self.disabled_extensions.contains(&EXTENSION_KEY_SHARE)
+        {
+            return builder
+                .with_protocol_versions(&[&rustls::version::TLS12])
+                .expect("The cipher suites should include TLS 1.2 ones.");
+        }
+
+        if self
+            .disabled_extensions
+            .contains(&EXTENSION_ENCRYPTED_CLIENT_HELLO)
+        {
+            return builder.with_safe_default_protocol_versions().unwrap();
+        }
+
+        // TODO - use the ECH extension consistently
+        builder.with_ech(self.get_ech_mode()).unwrap()
+    }
+
+    fn get_ech_mode(&self) -> rustls::client::EchMode {
         let (public_key, _) = statics::GREASE_HPKE_SUITE.generate_key_pair().unwrap();
 
         EchGreaseConfig::new(statics::GREASE_HPKE_SUITE, public_key).into()
@@ -53,27 +395,66 @@ impl TlsConfigBuilder {
         self
     }
 
+    /// Offers only `http/1.1` in the ALPN extension.
+    pub fn with_http1(&mut self) -> &mut Self {
+        self.max_http_version = Version::HTTP_11;
+        self
+    }
+
     pub fn with_ignore_tls_errors(&mut self, ignore_tls_errors: bool) -> &mut Self {
         self.ignore_tls_errors = ignore_tls_errors;
         self
     }
 
-    pub fn build(self) -> rustls::ClientConfig {
+    /// Sets the exact list (and order) of cipher suites offered in the ClientHello.
+    ///
+    /// If the list is empty, the cipher suites of the impersonated browser (or the `rustls` defaults) are used.
+    pub fn with_cipher_suites(&mut self, cipher_suites: &[SupportedCipherSuite]) -> &mut Self {
+        self.cipher_suites = cipher_suites.to_vec();
+        self
+    }
+
+    /// Enables sending TLS 1.3 early data (0-RTT) on resumed sessions.
+    pub fn with_early_data(&mut self, early_data: bool) -> &mut Self {
+        self.early_data = early_data;
+        self
+    }
+
+    /// Sets how the revocation status of the server certificates is checked.
+    ///
+    /// Has no effect with [`TlsConfigBuilder::with_ignore_tls_errors`].
+    pub fn with_revocation_checking(&mut self, mode: RevocationChecking) -> &mut Self {
+        self.revocation_checking = mode;
+        self
+    }
+
+    /// Counts the handshakes made with the built config in `counter`.
+    pub fn with_handshake_counter(&mut self, counter: Arc<HandshakeCounter>) -> &mut Self {
+        self.handshake_counter = Some(counter);
+        self
+    }
+
+    /// Enables or disables resuming the TLS sessions (with session tickets or TLS 1.3 PSKs).
+    pub fn with_session_resumption(&mut self, session_resumption: bool) -> &mut Self {
+        self.session_resumption = session_resumption;
+        self
+    }
+
+    /// Drops the extension with the given IANA ID (e.g. `0` for `server_name`) from the ClientHello.
+    ///
+    /// The ID should be checked with [`TlsConfig::check_removable_extension`] first, other extensions are kept.
+    pub fn without_extension(&mut self, extension_id: u16) -> &mut Self {
+        self.disabled_extensions.push(extension_id);
+        self
+    }
+
+    pub fn build(&self) -> rustls::ClientConfig {
         let mut root_store = RootCertStore::empty();
         root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
 
         let mut config = match self.browser {
             Some(browser) => {
-                let rustls_browser = match browser {
-                    Browser::Chrome => RusTLSBrowser {
-                        browser_type: BrowserType::Chrome,
-                        version: 125,
-                    },
-                    Browser::Firefox => RusTLSBrowser {
-                        browser_type: BrowserType::Firefox,
-                        version: 125,
-                    },
-                };
+                let rustls_browser = rustls_browser(browser);
 
                 let mut crypto_provider = CryptoProvider::builder()
                     .with_browser_emulator(&rustls_browser)
@@ -90,48 +471,273 @@ impl TlsConfigBuilder {
                     ];
                 }
 
-                let mut config: rustls::ClientConfig =
-                    rustls::ClientConfig::builder_with_provider(crypto_provider.into())
-                        // TODO - use the ECH extension consistently
-                        .with_ech(self.get_ech_mode())
-                        .unwrap()
-                        .with_root_certificates(root_store)
-                        .with_browser_emulator(&rustls_browser)
-                        .with_no_client_auth();
-
-                if self.ignore_tls_errors {
-                    config
-                        .dangerous()
-                        .set_certificate_verifier(Arc::new(NoVerifier::new(Some(rustls_browser))));
+                if !self.cipher_suites.is_empty() {
+                    crypto_provider.cipher_suites = self.cipher_suites.clone();
                 }
 
-                config
+                self.with_versions(rustls::ClientConfig::builder_with_provider(
+                    crypto_provider.into(),
+                ))
+                .with_root_certificates(root_store)
+                .with_browser_emulator(&rustls_browser)
+                .with_no_client_auth()
             }
             None => {
-                let crypto_provider = CryptoProvider::builder().build();
-
-                let mut config: rustls::ClientConfig =
-                    rustls::ClientConfig::builder_with_provider(crypto_provider.into())
-                        // TODO - use the ECH extension consistently
-                        .with_ech(self.get_ech_mode())
-                        .unwrap()
-                        .with_root_certificates(root_store)
-                        .with_no_client_auth();
-
-                if self.ignore_tls_errors {
-                    config
-                        .dangerous()
-                        .set_certificate_verifier(Arc::new(NoVerifier::new(None)));
+                let mut crypto_provider = CryptoProvider::builder().build();
+
+                if !self.cipher_suites.is_empty() {
+                    crypto_provider.cipher_suites = self.cipher_suites.clone();
                 }
 
-                config
+                self.with_versions(rustls::ClientConfig::builder_with_provider(
+                    crypto_provider.into(),
+                ))
+                .with_root_certificates(root_store)
+                .with_no_client_auth()
             }
         };
 
         if self.max_http_version == Version::HTTP_3 {
             config.alpn_protocols = vec![b"h3".to_vec()];
+        } else if self.max_http_version < Version::HTTP_2 {
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        };
+
+        // The verifier uses the signature schemes of the (browser-specific) crypto provider,
+        // so the `signature_algorithms` extension stays intact.
+        let provider = config.crypto_provider().clone();
+        let verifier: Arc<dyn ServerCertVerifier> = if self.ignore_tls_errors {
+            Arc::new(NoVerifier::new(self.browser.map(rustls_browser)))
+        } else {
+            match TlsConfig::revocation_verifier(&self.revocation_checking, provider.clone()) {
+                Ok(Some(verifier)) => verifier,
+                Ok(None) => TlsConfig::default_verifier(provider),
+                Err(e) => {
+                    warn!(
+                        "Couldn't set up the revocation checking, falling back to the default certificate verifier: {}",
+                        e
+                    );
+                    TlsConfig::default_verifier(provider)
+                }
+            }
         };
 
+        match &self.handshake_counter {
+            Some(handshake_counter) => {
+                config.key_log = handshake_counter.clone();
+                config
+                    .dangerous()
+                    .set_certificate_verifier(Arc::new(RecordingVerifier {
+                        inner: verifier,
+                        handshake_counter: handshake_counter.clone(),
+                    }));
+            }
+            None => config.dangerous().set_certificate_verifier(verifier),
+        }
+
+        config.enable_early_data =
+            self.early_data && !self.disabled_extensions.contains(&EXTENSION_EARLY_DATA);
+
+        if self.disabled_extensions.contains(&EXTENSION_SERVER_NAME) {
+            config.enable_sni = false;
+        }
+
+        // The extension lists the algorithms the server certificate chain can be compressed with.
+        if self
+            .disabled_extensions
+            .contains(&EXTENSION_COMPRESS_CERTIFICATE)
+        {
+            config.cert_decompressors = vec![];
+        }
+
+        if self.disabled_extensions.contains(&EXTENSION_SESSION_TICKET) {
+            config.resumption = rustls::client::Resumption::default()
+                .tls12_resumption(rustls::client::Tls12Resumption::SessionIdOnly);
+        }
+
+        if !self.session_resumption {
+            config.resumption = rustls::client::Resumption::disabled();
+        }
+
         config
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chrome_extensions(disabled_extensions: &[u16]) -> Vec<u16> {
+        let mut builder = TlsConfig::builder();
+        let mut builder = builder.with_browser(Some(Browser::Chrome));
+
+        for extension_id in disabled_extensions {
+            TlsConfig::check_removable_extension(*extension_id).unwrap();
+            builder = builder.without_extension(*extension_id);
+        }
+
+        let client_hello = capture_client_hello(builder.build(), "example.com").unwrap();
+        extension_types(&client_hello).unwrap()
+    }
+
+    #[test]
+    fn removable_extensions_are_omitted() {
+        let extensions = chrome_extensions(&[]);
+        assert!(extensions.contains(&EXTENSION_SERVER_NAME));
+        assert!(extensions.contains(&EXTENSION_ENCRYPTED_CLIENT_HELLO));
+
+        for extension_id in [
+            EXTENSION_SERVER_NAME,
+            EXTENSION_COMPRESS_CERTIFICATE,
+            EXTENSION_SESSION_TICKET,
+            EXTENSION_EARLY_DATA,
+            EXTENSION_ENCRYPTED_CLIENT_HELLO,
+        ] {
+            let extensions = chrome_extensions(&[extension_id]);
+            assert!(!extensions.contains(&extension_id), "{extension_id}");
+            assert!(extensions.contains(&EXTENSION_SUPPORTED_VERSIONS));
+        }
+    }
+
+    #[test]
+    fn critical_extensions_limit_the_handshake_to_tls12() {
+        let extensions = chrome_extensions(&[]);
+        assert!(extensions.contains(&EXTENSION_SUPPORTED_VERSIONS));
+        assert!(extensions.contains(&EXTENSION_KEY_SHARE));
+
+        for extension_id in [EXTENSION_SUPPORTED_VERSIONS, EXTENSION_KEY_SHARE] {
+            let extensions = chrome_extensions(&[extension_id]);
+            assert!(!extensions.contains(&EXTENSION_SUPPORTED_VERSIONS));
+            assert!(!extensions.contains(&EXTENSION_KEY_SHARE));
+            assert!(!extensions.contains(&EXTENSION_ENCRYPTED_CLIENT_HELLO));
+        }
+    }
+
+    fn offered_cipher_suites(browser: Option<Browser>, names: &[&str]) -> (Vec<u16>, String) {
+        let suites: Vec<_> = names
+            .iter()
+            .map(|name| TlsConfig::cipher_suite_from_name(name).unwrap())
+            .collect();
+
+        let mut builder = TlsConfig::builder();
+        builder.with_browser(browser).with_cipher_suites(&suites);
+
+        let client_hello = capture_client_hello(builder.build(), "example.com").unwrap();
+        (
+            cipher_suites(&client_hello).unwrap(),
+            ja4(&client_hello, false).unwrap(),
+        )
+    }
+
+    #[test]
+    fn configured_cipher_suites_are_offered_in_order() {
+        let names = [
+            "TLS13_CHACHA20_POLY1305_SHA256",
+            "TLS13_AES_128_GCM_SHA256",
+            "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+        ];
+
+        for browser in [None, Some(Browser::Chrome), Some(Browser::Firefox)] {
+            let (offered, ja4) = offered_cipher_suites(browser, &names);
+            assert_eq!(offered, [0x1303, 0x1301, 0xc030], "{browser:?}");
+            // The JA4 `a` part counts the cipher suites (without GREASE): `t13d03..`.
+            assert_eq!(&ja4[4..6], "03", "{browser:?}");
+
+            let mut reversed = names;
+            reversed.reverse();
+            let (offered, reversed_ja4) = offered_cipher_suites(browser, &reversed);
+            assert_eq!(offered, [0xc030, 0x1301, 0x1303], "{browser:?}");
+            // The JA4 hashes the sorted cipher suites, so the order doesn't change the fingerprint.
+            assert_eq!(reversed_ja4, ja4, "{browser:?}");
+        }
+    }
+
+    #[test]
+    fn chrome_sends_the_alps_extension() {
+        // `application_settings` (ALPS), sent by Chrome (but not Firefox) for the HTTP/2 settings.
+        assert!(chrome_extensions(&[]).contains(&17513));
+    }
+
+    #[test]
+    fn verified_chains_are_recorded_per_host() {
+        use rustls::KeyLog;
+
+        let handshake_counter = Arc::new(HandshakeCounter::default());
+        let verifier = RecordingVerifier {
+            inner: Arc::new(NoVerifier::new(None)),
+            handshake_counter: handshake_counter.clone(),
+        };
+
+        handshake_counter.log("CLIENT_HANDSHAKE_TRAFFIC_SECRET", &[0; 32], &[0; 48]);
+        verifier
+            .verify_server_cert(
+                &CertificateDer::from(vec![1, 2, 3]),
+                &[CertificateDer::from(vec![4, 5, 6])],
+                &ServerName::try_from("example.com").unwrap(),
+                &[],
+                UnixTime::now(),
+            )
+            .unwrap();
+
+        // The handshake isn't recorded until the server signature is checked.
+        assert!(handshake_counter.handshake("example.com").is_none());
+        handshake_counter.record(ProtocolVersion::TLSv1_3);
+
+        let handshake = handshake_counter.handshake("example.com").unwrap();
+        assert_eq!(handshake.version, ProtocolVersion::TLSv1_3);
+        assert_eq!(handshake.secret_length, Some(48));
+        assert_eq!(
+            handshake.certificate_chain,
+            vec![
+                CertificateDer::from(vec![1, 2, 3]),
+                CertificateDer::from(vec![4, 5, 6])
+            ]
+        );
+        assert_eq!(handshake_counter.count(), 1);
+        assert!(handshake_counter.handshake("example.org").is_none());
+    }
+
+    #[test]
+    fn cipher_suite_is_inferred_from_the_secret_length() {
+        let suite = |name| TlsConfig::cipher_suite_from_name(name).unwrap();
+        let handshake = |secret_length| HandshakeRecord {
+            version: ProtocolVersion::TLSv1_3,
+            secret_length: Some(secret_length),
+            certificate_chain: vec![],
+        };
+
+        let offered = [
+            suite("TLS13_AES_128_GCM_SHA256"),
+            suite("TLS13_AES_256_GCM_SHA384"),
+        ];
+        assert_eq!(
+            TlsConfig::negotiated_cipher_suite(&offered, &handshake(48)),
+            Some(CipherSuite::TLS13_AES_256_GCM_SHA384)
+        );
+        assert_eq!(
+            TlsConfig::negotiated_cipher_suite(&offered, &handshake(32)),
+            Some(CipherSuite::TLS13_AES_128_GCM_SHA256)
+        );
+
+        // Both suites use SHA-256.
+        let offered = [
+            suite("TLS13_AES_128_GCM_SHA256"),
+            suite("TLS13_CHACHA20_POLY1305_SHA256"),
+        ];
+        assert_eq!(
+            TlsConfig::negotiated_cipher_suite(&offered, &handshake(32)),
+            None
+        );
+    }
+
+    #[test]
+    fn unsupported_extensions_are_rejected() {
+        // `supported_groups`, `signature_algorithms` and `padding`
+        for extension_id in [10, 13, 21] {
+            assert!(matches!(
+                TlsConfig::check_removable_extension(extension_id),
+                Err(ErrorType::UnsupportedTlsExtension(id)) if id == extension_id
+            ));
+        }
+    }
+}
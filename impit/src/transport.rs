@@ -0,0 +1,296 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Request, Response, StatusCode,
+};
+
+use crate::impit::ErrorType;
+
+/// The future returned by [`Transport::execute`].
+pub type TransportFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Response, ErrorType>> + Send + 'a>>;
+
+/// Sends the requests prepared by [`Impit`](crate::impit::Impit) instead of the network clients, see [`ImpitBuilder::with_transport`](crate::impit::ImpitBuilder::with_transport).
+///
+/// The requests passed to [`Transport::execute`] have the impersonated headers (and the cookies) already set.
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: Request) -> TransportFuture<'_>;
+}
+
+/// A cloneable handle to the custom [`Transport`], stored in the [`ImpitBuilder`](crate::impit::ImpitBuilder).
+#[derive(Clone)]
+pub(crate) struct SharedTransport(pub(crate) Arc<dyn Transport>);
+
+impl fmt::Debug for SharedTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedTransport")
+    }
+}
+
+/// A stubbed response of the [`MockTransport`].
+#[derive(Clone)]
+struct MockResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// An in-memory [`Transport`] returning stubbed responses, for testing code using `impit` without network access.
+///
+/// The responses are matched by the exact request URL. Requests to other URLs get an empty `404 Not Found` response.
+/// The clones share the stubs and the recorded requests, so a clone kept by the test can inspect what was sent.
+///
+/// ### Example
+/// ```rust
+/// let transport = MockTransport::new().with_response(
+///     "https://example.com/",
+///     StatusCode::OK,
+///     &[("content-type", "text/html")],
+///     "<h1>Hello</h1>",
+/// );
+///
+/// let mut impit = Impit::builder()
+///     .with_transport(Box::new(transport))
+///     .build();
+///
+/// let response = impit.get("https://example.com/".to_string(), None).await?;
+/// assert_eq!(response.text().await?, "<h1>Hello</h1>");
+/// ```
+#[derive(Default, Clone)]
+pub struct MockTransport {
+    responses: Arc<Mutex<HashMap<String, VecDeque<MockResponse>>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stubs the response for `url`. Invalid header names and values are skipped.
+    ///
+    /// Stubbing the same URL again queues another response, e.g. a `401` challenge followed by a `200`.
+    /// The queued responses are returned in order, the last one for all the remaining requests.
+    pub fn with_response(
+        self,
+        url: &str,
+        status: StatusCode,
+        headers: &[(&str, &str)],
+        body: impl Into<Bytes>,
+    ) -> Self {
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect();
+
+        // The URL is normalized the same way as the request URLs, e.g. `https://example.com` becomes `https://example.com/`.
+        let url = url::Url::parse(url)
+            .map(String::from)
+            .unwrap_or_else(|_| url.to_owned());
+
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(url)
+            .or_default()
+            .push_back(MockResponse {
+                status,
+                headers,
+                body: body.into(),
+            });
+        self
+    }
+
+    /// Returns the requests sent so far, in order. The requests with streamed bodies are left out.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Request::try_clone)
+            .collect()
+    }
+}
+
+impl Transport for MockTransport {
+    fn execute(&self, request: Request) -> TransportFuture<'_> {
+        let stub = self
+            .responses
+            .lock()
+            .unwrap()
+            .get_mut(request.url().as_str())
+            .and_then(|stubs| match stubs.len() {
+                1 => stubs.front().cloned(),
+                _ => stubs.pop_front(),
+            });
+
+        let response = match stub {
+            Some(stub) => {
+                let mut response = http::Response::new(stub.body.clone());
+                *response.status_mut() = stub.status;
+                *response.headers_mut() = stub.headers.clone();
+                response
+            }
+            None => {
+                let mut response = http::Response::new(Bytes::new());
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                response
+            }
+        };
+
+        self.requests.lock().unwrap().push(request);
+
+        Box::pin(async move { Ok(Response::from(response)) })
+    }
+}
+
+/// A [`Transport`] sending the requests over a Unix domain socket, see [`ImpitBuilder::with_unix_socket`](crate::impit::ImpitBuilder::with_unix_socket).
+///
+/// Every request opens a new connection (speaking HTTP/1.1) to the socket. The URL host is only used for the `Host` header.
+#[cfg(all(unix, feature = "unix-socket"))]
+pub struct UnixSocketTransport {
+    path: std::path::PathBuf,
+}
+
+#[cfg(all(unix, feature = "unix-socket"))]
+impl UnixSocketTransport {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        UnixSocketTransport { path: path.into() }
+    }
+
+    async fn send(&self, request: Request) -> Result<Response, ErrorType> {
+        let url = request.url().clone();
+        let mut request: http::Request<reqwest::Body> =
+            request.try_into().map_err(ErrorType::RequestError)?;
+
+        // The request goes straight to the server, so the target is in the origin form (`/path?query`).
+        let origin_form = url[url::Position::BeforePath..url::Position::AfterQuery].to_owned();
+        *request.uri_mut() = origin_form.parse()?;
+
+        if !request.headers().contains_key(reqwest::header::HOST) {
+            let host = &url[url::Position::BeforeHost..url::Position::AfterPort];
+            request.headers_mut().insert(
+                reqwest::header::HOST,
+                HeaderValue::from_str(host).map_err(http::Error::from)?,
+            );
+        }
+
+        let stream = tokio::net::UnixStream::connect(&self.path)
+            .await
+            .map_err(ErrorType::UnixSocketError)?;
+
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(stream))
+                .await
+                .map_err(|e| ErrorType::UnixSocketError(std::io::Error::other(e)))?;
+
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::debug!("The Unix socket connection failed: {}", e);
+            }
+        });
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| ErrorType::UnixSocketError(std::io::Error::other(e)))?;
+
+        let response = response
+            .map(|body| reqwest::Body::wrap_stream(http_body_util::BodyDataStream::new(body)));
+
+        Ok(Response::from(response))
+    }
+}
+
+#[cfg(all(unix, feature = "unix-socket"))]
+impl Transport for UnixSocketTransport {
+    fn execute(&self, request: Request) -> TransportFuture<'_> {
+        Box::pin(self.send(request))
+    }
+}
+
+#[cfg(all(test, unix, feature = "unix-socket"))]
+mod tests {
+    use super::*;
+    use crate::impit::Impit;
+
+    /// Returns a socket path in the temporary directory, unique for every test.
+    fn socket_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("impit-{}-{name}.sock", std::process::id()))
+    }
+
+    /// Responds with the request head as the body, on a Unix socket at `path`.
+    fn serve_unix_socket(path: &std::path::Path) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let _ = std::fs::remove_file(path);
+        let listener = tokio::net::UnixListener::bind(path).unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut head = vec![];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0u8];
+                    if stream.read_exact(&mut byte).await.is_err() {
+                        break;
+                    }
+                    head.push(byte[0]);
+                }
+
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    head.len(),
+                    String::from_utf8_lossy(&head)
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn requests_are_sent_over_the_unix_socket() {
+        let path = socket_path("get");
+        serve_unix_socket(&path);
+        let mut impit = Impit::builder().with_unix_socket(&path).build();
+
+        let response = impit
+            .get("http://docker/containers/json?all=1".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let head = response.text().await.unwrap().to_ascii_lowercase();
+        assert!(
+            head.starts_with("get /containers/json?all=1 http/1.1\r\n"),
+            "{head}"
+        );
+        assert!(head.contains("\r\nhost: docker\r\n"), "{head}");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn missing_sockets_fail_with_the_unix_socket_error() {
+        let path = socket_path("missing");
+        let _ = std::fs::remove_file(&path);
+        let mut impit = Impit::builder().with_unix_socket(&path).build();
+
+        assert!(matches!(
+            impit.get("http://localhost/".to_string(), None).await,
+            Err(ErrorType::UnixSocketError(_))
+        ));
+    }
+}
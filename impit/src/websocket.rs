@@ -0,0 +1,648 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use log::debug;
+use tokio::{
+    net::TcpStream,
+    time::{Instant, Sleep},
+};
+use tokio_tungstenite::{
+    tungstenite::{self, handshake::client::Response, http::StatusCode, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    impit::{ErrorType, Impit},
+    request::RequestOptions,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The pending (re)connection, owning the [`Impit`] instance until it finishes.
+type Connecting = Pin<Box<dyn Future<Output = (Impit, Result<Socket, ErrorType>)> + Send>>;
+
+/// The settings of the reconnection attempts, see [`ReconnectingWebSocket::with_backoff`].
+#[derive(Debug, Clone)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    max_retries: Option<usize>,
+}
+
+/// A WebSocket connection that transparently reconnects when the server closes it or the connection fails.
+///
+/// After every (re)connection, the configured subscription messages are re-sent, so the consumers of long-lived feeds
+/// don't have to handle reconnects themselves. Failed connection attempts (including failing to send the subscriptions)
+/// are retried with exponential backoff.
+///
+/// The socket implements [`Stream`] and [`Sink`], so it can be used with the `StreamExt` and `SinkExt` combinators.
+///
+/// ### Example
+/// ```rust
+/// let mut socket = ReconnectingWebSocket::new(impit, "wss://example.com/feed".to_string(), None)
+///     .with_subscriptions(vec![Message::text("{\"subscribe\": \"prices\"}")]);
+///
+/// while let Some(message) = socket.next().await {
+///     println!("{:?}", message?);
+/// }
+/// ```
+pub struct ReconnectingWebSocket {
+    /// The `Impit` instance, `None` while a connection attempt is in progress.
+    impit: Option<Impit>,
+    url: String,
+    options: Option<RequestOptions>,
+    subscriptions: Vec<Message>,
+    backoff: Backoff,
+    socket: Option<Socket>,
+    connecting: Option<Connecting>,
+}
+
+impl ReconnectingWebSocket {
+    /// Creates a new reconnecting WebSocket for the given URL. The connection is opened lazily, when the socket is first polled.
+    pub fn new(impit: Impit, url: String, options: Option<RequestOptions>) -> Self {
+        ReconnectingWebSocket {
+            impit: Some(impit),
+            url,
+            options,
+            subscriptions: vec![],
+            backoff: Backoff {
+                initial: Duration::from_millis(500),
+                max: Duration::from_secs(30),
+                max_retries: None,
+            },
+            socket: None,
+            connecting: None,
+        }
+    }
+
+    /// Sets the messages sent to the server after every (re)connection, e.g. subscription requests.
+    pub fn with_subscriptions(mut self, subscriptions: Vec<Message>) -> Self {
+        self.subscriptions = subscriptions;
+        self
+    }
+
+    /// Sets the delay before the first reconnection attempt and the maximum delay between the attempts.
+    ///
+    /// The delay doubles after every failed attempt. Defaults to 500 ms and 30 seconds.
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.backoff.initial = initial_backoff;
+        self.backoff.max = max_backoff;
+        self
+    }
+
+    /// Sets the maximum number of consecutive failed connection attempts before the last error is returned.
+    ///
+    /// By default, the socket keeps trying to reconnect indefinitely.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.backoff.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Closes the connection without reconnecting.
+    pub async fn close(mut self) -> Result<(), ErrorType> {
+        if let Some(mut socket) = self.socket.take() {
+            socket.close(None).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Polls the pending (re)connection, starting one if there is no open connection.
+    fn poll_connected(&mut self, cx: &mut Context<'_>) -> Poll<Result<&mut Socket, ErrorType>> {
+        if self.socket.is_none() {
+            if self.connecting.is_none() {
+                let mut impit = self
+                    .impit
+                    .take()
+                    .expect("the Impit instance should be available when not connecting");
+                let url = self.url.clone();
+                let options = self.options.clone();
+                let subscriptions = self.subscriptions.clone();
+                let backoff = self.backoff.clone();
+
+                self.connecting = Some(Box::pin(async move {
+                    let result = connect(&mut impit, &url, options, &subscriptions, &backoff).await;
+                    (impit, result)
+                }));
+            }
+
+            let connecting = self
+                .connecting
+                .as_mut()
+                .expect("the connection attempt should be in progress");
+            let (impit, result) = ready!(connecting.as_mut().poll(cx));
+
+            self.connecting = None;
+            self.impit = Some(impit);
+
+            match result {
+                Ok(socket) => self.socket = Some(socket),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+
+        Poll::Ready(Ok(self
+            .socket
+            .as_mut()
+            .expect("the socket should be connected")))
+    }
+}
+
+/// Opens the socket and sends the subscriptions, retrying the failed attempts with exponential backoff.
+async fn connect(
+    impit: &mut Impit,
+    url: &str,
+    options: Option<RequestOptions>,
+    subscriptions: &[Message],
+    backoff: &Backoff,
+) -> Result<Socket, ErrorType> {
+    let mut delay = backoff.initial;
+    let mut failed_attempts = 0;
+
+    loop {
+        let attempt = async {
+            let (mut socket, _) = impit.open_socket(url.to_string(), options.clone()).await?;
+
+            for message in subscriptions {
+                socket.send(message.clone()).await?;
+            }
+
+            Ok::<_, ErrorType>(socket)
+        };
+
+        match attempt.await {
+            Ok(socket) => return Ok(socket),
+            Err(e) => {
+                failed_attempts += 1;
+
+                if backoff.max_retries.is_some_and(|max| failed_attempts > max) {
+                    return Err(e);
+                }
+
+                debug!(
+                    "Couldn't connect to {}, retrying in {:?}: {}",
+                    url, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(backoff.max);
+            }
+        }
+    }
+}
+
+/// Receives the messages, reconnecting if the connection was closed or failed.
+///
+/// Yields an error if the reconnection fails more than `max_retries` times.
+impl Stream for ReconnectingWebSocket {
+    type Item = Result<Message, ErrorType>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let socket = match ready!(this.poll_connected(cx)) {
+                Ok(socket) => socket,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+
+            match ready!(socket.poll_next_unpin(cx)) {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    debug!("The connection to {} was closed, reconnecting", this.url);
+                    this.socket = None;
+                }
+                Some(Ok(message)) => return Poll::Ready(Some(Ok(message))),
+            }
+        }
+    }
+}
+
+/// Sends the messages, reconnecting if the connection was closed or failed.
+impl Sink<Message> for ReconnectingWebSocket {
+    type Error = ErrorType;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        loop {
+            let socket = ready!(this.poll_connected(cx))?;
+
+            match ready!(socket.poll_ready_unpin(cx)) {
+                Ok(()) => return Poll::Ready(Ok(())),
+                Err(e) => {
+                    debug!("The connection to {} failed, reconnecting: {}", this.url, e);
+                    this.socket = None;
+                }
+            }
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        match self.get_mut().socket.as_mut() {
+            Some(socket) => Ok(socket.start_send_unpin(message)?),
+            None => Err(tungstenite::Error::ConnectionClosed.into()),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut().socket.as_mut() {
+            Some(socket) => socket.poll_flush_unpin(cx).map_err(ErrorType::from),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut().socket.as_mut() {
+            Some(socket) => socket.poll_close_unpin(cx).map_err(ErrorType::from),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// Options for the WebSocket keepalive, see [`KeepaliveWebSocket`].
+#[derive(Debug, Clone)]
+pub struct WebSocketOptions {
+    /// How often to send a `Ping` frame. `None` disables the keepalive.
+    pub ping_interval: Option<Duration>,
+    /// How long to wait for the `Pong` frame after sending a `Ping`, before treating the connection as dead.
+    pub pong_timeout: Duration,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        WebSocketOptions {
+            ping_interval: None,
+            pong_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A WebSocket connection that sends periodic `Ping` frames to keep idle connections alive.
+///
+/// Idle connections going through NATs or proxies are often dropped silently. With the keepalive,
+/// a missing `Pong` within the `pong_timeout` is reported as the [`ErrorType::WebsocketPongTimeout`] error.
+///
+/// ### Example
+/// ```rust
+/// let (socket, _) = impit.open_socket("wss://example.com/feed".to_string(), None).await?;
+/// let mut socket = KeepaliveWebSocket::new(socket, WebSocketOptions {
+///     ping_interval: Some(Duration::from_secs(15)),
+///     ..Default::default()
+/// });
+///
+/// while let Some(message) = socket.next().await {
+///     println!("{:?}", message?);
+/// }
+/// ```
+pub struct KeepaliveWebSocket {
+    socket: Socket,
+    options: WebSocketOptions,
+    next_ping: Instant,
+    pong_deadline: Option<Instant>,
+    /// Wakes the task at the `pong_deadline` or `next_ping`, whichever applies.
+    timer: Pin<Box<Sleep>>,
+    /// Whether a `Ping` is due, but the socket wasn't ready to send it yet.
+    ping_pending: bool,
+    /// Whether a sent `Ping` still has to be flushed.
+    flush_pending: bool,
+}
+
+impl KeepaliveWebSocket {
+    pub fn new(socket: Socket, options: WebSocketOptions) -> Self {
+        let next_ping = Instant::now() + options.ping_interval.unwrap_or_default();
+
+        KeepaliveWebSocket {
+            socket,
+            options,
+            next_ping,
+            pong_deadline: None,
+            timer: Box::pin(tokio::time::sleep_until(next_ping)),
+            ping_pending: false,
+            flush_pending: false,
+        }
+    }
+
+    /// Returns the underlying WebSocket stream.
+    pub fn into_inner(self) -> Socket {
+        self.socket
+    }
+
+    /// Sends (and flushes) the pending `Ping` frame, as far as the socket allows without blocking.
+    fn poll_ping(
+        &mut self,
+        cx: &mut Context<'_>,
+        ping_interval: Duration,
+    ) -> Result<(), ErrorType> {
+        if self.ping_pending {
+            if let Poll::Ready(ready) = self.socket.poll_ready_unpin(cx) {
+                ready?;
+                self.socket
+                    .start_send_unpin(Message::Ping(Default::default()))?;
+
+                let now = Instant::now();
+                self.pong_deadline = Some(now + self.options.pong_timeout);
+                self.next_ping = now + ping_interval;
+                self.ping_pending = false;
+                self.flush_pending = true;
+            }
+        }
+
+        if self.flush_pending {
+            if let Poll::Ready(flushed) = self.socket.poll_flush_unpin(cx) {
+                flushed?;
+                self.flush_pending = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Receives the messages, sending `Ping` frames in the meantime.
+///
+/// Ends if the connection was closed. A missing `Pong` is reported as the [`ErrorType::WebsocketPongTimeout`] error.
+impl Stream for KeepaliveWebSocket {
+    type Item = Result<Message, ErrorType>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let Some(ping_interval) = this.options.ping_interval else {
+            return this
+                .socket
+                .poll_next_unpin(cx)
+                .map(|message| message.map(|message| message.map_err(ErrorType::from)));
+        };
+
+        loop {
+            if let Err(e) = this.poll_ping(cx, ping_interval) {
+                return Poll::Ready(Some(Err(e)));
+            }
+
+            if let Poll::Ready(message) = this.socket.poll_next_unpin(cx) {
+                if let Some(Ok(Message::Pong(_))) = message {
+                    this.pong_deadline = None;
+                }
+
+                return Poll::Ready(message.map(|message| message.map_err(ErrorType::from)));
+            }
+
+            // The socket wakes the task once it's ready to send the pending `Ping`.
+            if this.ping_pending {
+                return Poll::Pending;
+            }
+
+            let deadline = this.pong_deadline.unwrap_or(this.next_ping);
+            if this.timer.deadline() != deadline {
+                this.timer.as_mut().reset(deadline);
+            }
+
+            ready!(this.timer.as_mut().poll(cx));
+
+            if this.pong_deadline.is_some() {
+                return Poll::Ready(Some(Err(ErrorType::WebsocketPongTimeout)));
+            }
+
+            this.ping_pending = true;
+        }
+    }
+}
+
+impl Sink<Message> for KeepaliveWebSocket {
+    type Error = ErrorType;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut()
+            .socket
+            .poll_ready_unpin(cx)
+            .map_err(ErrorType::from)
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: Message) -> Result<(), Self::Error> {
+        Ok(self.get_mut().socket.start_send_unpin(message)?)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut()
+            .socket
+            .poll_flush_unpin(cx)
+            .map_err(ErrorType::from)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut()
+            .socket
+            .poll_close_unpin(cx)
+            .map_err(ErrorType::from)
+    }
+}
+
+/// A summary of the WebSocket handshake (upgrade) response returned by [`Impit::open_socket`](crate::impit::Impit::open_socket).
+///
+/// ### Example
+/// ```rust
+/// let (socket, response) = impit.open_socket("wss://example.com/feed".to_string(), None).await?;
+/// let handshake = WebSocketHandshake::from(&response);
+///
+/// println!("{:?} {:?}", handshake.subprotocol, handshake.cookie("session"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WebSocketHandshake {
+    /// The status code of the upgrade response (usually `101 Switching Protocols`).
+    pub status: StatusCode,
+    /// The subprotocol selected by the server (the `Sec-WebSocket-Protocol` header).
+    pub subprotocol: Option<String>,
+    /// The extensions negotiated by the server (the `Sec-WebSocket-Extensions` header), e.g. `permessage-deflate`.
+    pub extensions: Vec<String>,
+    /// The raw values of the `Set-Cookie` headers sent with the upgrade response.
+    pub set_cookies: Vec<String>,
+}
+
+impl WebSocketHandshake {
+    /// Returns the value of the cookie called `name` set by the upgrade response.
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.set_cookies.iter().find_map(|set_cookie| {
+            let (cookie_name, value) = set_cookie.split(';').next()?.split_once('=')?;
+
+            if cookie_name.trim() == name {
+                Some(value.trim())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl From<&Response> for WebSocketHandshake {
+    fn from(response: &Response) -> Self {
+        let headers = response.headers();
+
+        let subprotocol = headers
+            .get("sec-websocket-protocol")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_owned());
+
+        let extensions = headers
+            .get_all("sec-websocket-extensions")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|extension| extension.trim().to_owned())
+            .filter(|extension| !extension.is_empty())
+            .collect();
+
+        let set_cookies = headers
+            .get_all("set-cookie")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+            .collect();
+
+        WebSocketHandshake {
+            status: response.status(),
+            subprotocol,
+            extensions,
+            set_cookies,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn reconnects_and_resubscribes_after_the_server_closes_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                let subscription = socket.next().await.unwrap().unwrap();
+                let reply = format!("{}: {}", connection, subscription.to_text().unwrap());
+
+                socket.send(Message::text(reply)).await.unwrap();
+                socket.close(None).await.unwrap();
+            }
+        });
+
+        let mut socket =
+            ReconnectingWebSocket::new(Impit::default(), format!("ws://{}", addr), None)
+                .with_subscriptions(vec![Message::text("prices")])
+                .with_backoff(Duration::from_millis(10), Duration::from_millis(10));
+
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            Message::text("0: prices")
+        );
+        assert_eq!(
+            socket.next().await.unwrap().unwrap(),
+            Message::text("1: prices")
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut socket =
+            ReconnectingWebSocket::new(Impit::default(), format!("ws://{}", addr), None)
+                .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+                .with_max_retries(2);
+
+        assert!(socket.next().await.unwrap().is_err());
+        assert!(socket.send(Message::text("ping")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn pings_are_sent_at_the_interval() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut pings = 0;
+
+            // `tungstenite` answers the pings with pongs on its own.
+            while let Some(Ok(message)) = socket.next().await {
+                if message.is_ping() {
+                    pings += 1;
+                }
+
+                if pings == 3 {
+                    socket.send(Message::text("done")).await.unwrap();
+                }
+            }
+        });
+
+        let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let mut socket = KeepaliveWebSocket::new(
+            socket,
+            WebSocketOptions {
+                ping_interval: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        );
+
+        let started = Instant::now();
+        let mut pongs = 0;
+
+        loop {
+            match socket.next().await.unwrap().unwrap() {
+                Message::Pong(_) => pongs += 1,
+                message => {
+                    assert_eq!(message, Message::text("done"));
+                    break;
+                }
+            }
+        }
+
+        assert!(pongs >= 2);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn missing_pongs_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+            // Never reads the pings, so no pongs are sent.
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let mut socket = KeepaliveWebSocket::new(
+            socket,
+            WebSocketOptions {
+                ping_interval: Some(Duration::from_millis(20)),
+                pong_timeout: Duration::from_millis(20),
+            },
+        );
+
+        assert!(matches!(
+            socket.next().await,
+            Some(Err(ErrorType::WebsocketPongTimeout))
+        ));
+    }
+}